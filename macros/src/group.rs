@@ -0,0 +1,233 @@
+use crate::{check_sig, outer_sig, output, Elided};
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_quote,
+    visit_mut::{self, VisitMut},
+    Error,
+    Expr,
+    FnArg,
+    Ident,
+    Item,
+    ItemFn,
+    Lifetime,
+    Macro,
+    Stmt,
+};
+
+/// The functions given to `tramp_group!`.
+pub struct Group {
+    funs: Vec<ItemFn>,
+}
+
+impl Parse for Group {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut funs = Vec::new();
+        while !input.is_empty() {
+            funs.push(input.parse()?);
+        }
+        Ok(Self { funs })
+    }
+}
+
+/// Expands a group of mutually recursive functions into an enum with a
+/// variant per function, holding its arguments, whose `step` runs the body
+/// of the function. `rec_call!` in the bodies turns into the next state and
+/// `rec_ret!` into the result, and each function becomes a wrapper running
+/// the enum from its own state with `tramp`.
+pub fn expand(group: Group) -> syn::Result<TokenStream> {
+    let first = match group.funs.first() {
+        Some(first) => first,
+        None => {
+            return Err(Error::new(
+                Span::call_site(),
+                "tramp_group! needs at least one function",
+            ))
+        },
+    };
+    let state = format_ident!("__TrampGroup_{}", first.sig.ident);
+    let ret = output(&first.sig);
+    let ret_str = quote!(#ret).to_string();
+    let names =
+        group.funs.iter().map(|fun| &fun.sig.ident).collect::<Vec<_>>();
+
+    // Arguments with elided lifetimes are held by the enum, which then
+    // needs a lifetime.
+    let lifetime = Lifetime::new("'__tramp", Span::call_site());
+    let mut elided = Elided::new(&lifetime);
+    let mut inner_output = ret.clone();
+    elided.visit_type_mut(&mut inner_output);
+    let mut inner_sigs = Vec::with_capacity(group.funs.len());
+    for fun in &group.funs {
+        check_sig(&fun.sig, "tramp_group!")?;
+        if !fun.sig.generics.params.is_empty() {
+            return Err(Error::new_spanned(
+                &fun.sig.generics,
+                "tramp_group! does not support generic functions",
+            ));
+        }
+        let fun_ret = output(&fun.sig);
+        if quote!(#fun_ret).to_string() != ret_str {
+            return Err(Error::new_spanned(
+                &fun.sig.output,
+                "all functions of a tramp_group! must return the same type",
+            ));
+        }
+        let mut sig = fun.sig.clone();
+        for input in sig.inputs.iter_mut() {
+            if let FnArg::Typed(arg) = input {
+                elided.visit_type_mut(&mut arg.ty);
+            }
+        }
+        inner_sigs.push(sig);
+    }
+    let generics = if elided.used { quote!(<#lifetime>) } else { quote!() };
+    let step_output = quote! {
+        ::core::ops::ControlFlow<#inner_output, #state #generics>
+    };
+
+    let mut variants = Vec::new();
+    let mut arms = Vec::new();
+    let mut wrappers = Vec::new();
+    for (fun, mut sig) in group.funs.iter().zip(inner_sigs) {
+        let name = &fun.sig.ident;
+        let types = sig.inputs.iter().filter_map(|input| match input {
+            FnArg::Typed(arg) => Some(&arg.ty),
+            FnArg::Receiver(_) => None,
+        });
+        variants.push(quote!(#name(#(#types),*)));
+
+        let mut body = (*fun.block).clone();
+        RecMacros { state: &state, names: &names }.visit_block_mut(&mut body);
+        sig.ident = format_ident!("__tramp_step_{}", name);
+        sig.generics = parse_quote!(#generics);
+        sig.output = parse_quote!(-> #step_output);
+        let step = &sig.ident;
+        let bindings = (0..sig.inputs.len())
+            .map(|i| format_ident!("__tramp_arg{}", i))
+            .collect::<Vec<_>>();
+        arms.push(quote! {
+            #state::#name(#(#bindings),*) => {
+                #[allow(unreachable_code)]
+                #sig #body
+
+                #step(#(#bindings),*)
+            }
+        });
+
+        let attrs = &fun.attrs;
+        let vis = &fun.vis;
+        let (outer_sig, args) = outer_sig(&fun.sig);
+        wrappers.push(quote! {
+            #(#attrs)*
+            #vis #outer_sig {
+                ::tramp::tramp(#state::#name(#(#args),*))
+            }
+        });
+    }
+
+    Ok(quote! {
+        #[allow(non_camel_case_types)]
+        enum #state #generics {
+            #(#variants),*
+        }
+
+        impl #generics ::tramp::Trampoline for #state #generics {
+            type Output = #inner_output;
+
+            #[inline]
+            fn step(self) -> #step_output {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+
+        #(#wrappers)*
+    })
+}
+
+/// Rewrites `rec_call!` and `rec_ret!` in the body of a function of the
+/// group.
+struct RecMacros<'s> {
+    state: &'s Ident,
+    names: &'s [&'s Ident],
+}
+
+impl RecMacros<'_> {
+    fn rewrite(&mut self, mac: &Macro) -> Option<Expr> {
+        let name = &mac.path.segments.last()?.ident;
+        let result = if name == "rec_call" {
+            self.rewrite_call(mac)
+        } else if name == "rec_ret" {
+            self.rewrite_ret(mac)
+        } else {
+            return None;
+        };
+        Some(result.unwrap_or_else(|error| {
+            Expr::Verbatim(error.to_compile_error())
+        }))
+    }
+
+    fn rewrite_call(&mut self, mac: &Macro) -> syn::Result<Expr> {
+        let mut call = match mac.parse_body::<Expr>()? {
+            Expr::Call(call) => call,
+            other => return Err(self.not_in_group(&other)),
+        };
+        let name = match &*call.func {
+            Expr::Path(path) => match path.path.get_ident() {
+                Some(name) if self.names.contains(&name) => name.clone(),
+                _ => return Err(self.not_in_group(&call.func)),
+            },
+            _ => return Err(self.not_in_group(&call.func)),
+        };
+        for arg in &mut call.args {
+            self.visit_expr_mut(arg);
+        }
+        let state = self.state;
+        let args = &call.args;
+        Ok(parse_quote! {
+            return ::core::ops::ControlFlow::Continue(#state::#name(#args))
+        })
+    }
+
+    fn rewrite_ret(&mut self, mac: &Macro) -> syn::Result<Expr> {
+        let mut value = mac.parse_body::<Expr>()?;
+        self.visit_expr_mut(&mut value);
+        Ok(parse_quote! {
+            return ::core::ops::ControlFlow::Break(#value)
+        })
+    }
+
+    fn not_in_group(&self, tokens: impl quote::ToTokens) -> Error {
+        Error::new_spanned(
+            tokens,
+            "rec_call! in tramp_group! can only call a function of the group",
+        )
+    }
+}
+
+impl VisitMut for RecMacros<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        if let Expr::Macro(mac) = expr {
+            if let Some(rewritten) = self.rewrite(&mac.mac) {
+                *expr = rewritten;
+                return;
+            }
+        }
+        visit_mut::visit_expr_mut(self, expr);
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        if let Stmt::Macro(mac) = stmt {
+            if let Some(rewritten) = self.rewrite(&mac.mac) {
+                *stmt = Stmt::Expr(rewritten, mac.semi_token);
+                return;
+            }
+        }
+        visit_mut::visit_stmt_mut(self, stmt);
+    }
+
+    fn visit_item_mut(&mut self, _: &mut Item) {}
+}
@@ -0,0 +1,546 @@
+//! This crate provides the `#[tramp]` attribute, which turns a plain
+//! tail-recursive function into a trampolined one, so that it recurses in
+//! constant native stack.
+//!
+//! Take the factorial with an accumulator from the `tramp` crate's
+//! documentation. Instead of changing its return type to `Rec`, wrapping
+//! every tail call in `rec_call!`, every returned value in `rec_ret!` and
+//! writing a wrapper calling `tramp`, it is enough to annotate it:
+//!
+//! ```rust
+//! use tramp_macros::tramp;
+//!
+//! #[tramp]
+//! fn fac_with_acc(n: u128, acc: u128) -> u128 {
+//!     if n > 1 {
+//!         fac_with_acc(n - 1, acc * n)
+//!     } else {
+//!         acc
+//!     }
+//! }
+//!
+//! assert_eq!(fac_with_acc(5, 1), 120);
+//! ```
+//!
+//! The function keeps its signature: the attribute emits an inner function
+//! returning `BorrowRec`, in which every call of the function to itself in
+//! tail position becomes a `BorrowRec::Call` and every other returned value
+//! a `BorrowRec::Ret`, and makes the original function run it with `tramp`.
+//! The arguments of a tail call are evaluated before the call is deferred,
+//! as they would be without the attribute.
+//!
+//! A call of the function to itself which is not in tail position would
+//! still take native stack, so it is reported as an error:
+//!
+//! ```rust,compile_fail
+//! use tramp_macros::tramp;
+//!
+//! #[tramp]
+//! fn fac(n: u128) -> u128 {
+//!     if n > 1 {
+//!         n * fac(n - 1)
+//!     } else {
+//!         1
+//!     }
+//! }
+//! ```
+//!
+//! In a function returning a `Result` or an `Option`, the `?` operator ends
+//! the whole recursion, as expected. Only calls of the function to itself
+//! are rewritten; mutual recursion is the job of `tramp_group!`. The
+//! generated code refers to the `tramp` crate as `::tramp`.
+//!
+//! # Mutual recursion
+//! A group of mutually recursive functions can be given to `tramp_group!`,
+//! using `rec_call!` and `rec_ret!` in their bodies as with `Rec`, but
+//! keeping their signatures. Since all calls the functions can make are
+//! known, the group is compiled to an enum holding the arguments of each
+//! function and a loop matching on it, instead of boxed closures: the
+//! recursion does not allocate at all.
+//!
+//! ```rust
+//! use tramp_macros::tramp_group;
+//!
+//! tramp_group! {
+//!     fn is_even(x: u128) -> bool {
+//!         if x > 0 {
+//!             rec_call!(is_odd(x - 1))
+//!         } else {
+//!             rec_ret!(true)
+//!         }
+//!     }
+//!
+//!     fn is_odd(x: u128) -> bool {
+//!         if x > 0 {
+//!             rec_call!(is_even(x - 1))
+//!         } else {
+//!             rec_ret!(false)
+//!         }
+//!     }
+//! }
+//!
+//! assert!(is_even(100000));
+//! assert!(is_odd(100001));
+//! ```
+//!
+//! The functions must all return the same type and cannot be generic.
+//! `rec_call!` can only call functions of the group.
+extern crate proc_macro;
+
+mod group;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input,
+    parse_quote,
+    parse_quote_spanned,
+    spanned::Spanned,
+    visit_mut::{self, VisitMut},
+    Block,
+    Error,
+    Expr,
+    ExprCall,
+    ExprTry,
+    FnArg,
+    GenericParam,
+    Ident,
+    Item,
+    ItemFn,
+    Lifetime,
+    ParenthesizedGenericArguments,
+    ReturnType,
+    Signature,
+    Stmt,
+    Type,
+    TypeBareFn,
+    TypeImplTrait,
+    TypeParamBound,
+    TypeReference,
+};
+
+/// Turns a tail-recursive function into a trampolined one. See the crate
+/// documentation.
+#[proc_macro_attribute]
+pub fn tramp(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        let attr = proc_macro2::TokenStream::from(attr);
+        return Error::new_spanned(attr, "#[tramp] takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+    let fun = parse_macro_input!(item as ItemFn);
+    match expand(fun) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+/// Turns a group of mutually recursive functions into a state machine,
+/// trampolined without allocating. See the crate documentation.
+#[proc_macro]
+pub fn tramp_group(input: TokenStream) -> TokenStream {
+    let group = parse_macro_input!(input as group::Group);
+    match group::expand(group) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand(fun: ItemFn) -> syn::Result<proc_macro2::TokenStream> {
+    let ItemFn { attrs, vis, sig, block } = fun;
+    check_sig(&sig, "#[tramp]")?;
+    let (outer_sig, args) = outer_sig(&sig);
+
+    let output = output(&sig);
+    let try_kind = TryKind::of(&output);
+
+    // The thunks of the inner function capture its arguments, so they may
+    // only live as long as all of them: give elided lifetimes a name and
+    // bound every generic parameter by it.
+    let lifetime = Lifetime::new("'__tramp", Span::call_site());
+    let mut inner_sig = sig.clone();
+    let mut elided = Elided::new(&lifetime);
+    for input in inner_sig.inputs.iter_mut() {
+        if let FnArg::Typed(arg) = input {
+            elided.visit_type_mut(&mut arg.ty);
+        }
+    }
+    let mut inner_output = output;
+    elided.visit_type_mut(&mut inner_output);
+    inner_sig.output =
+        parse_quote!(-> ::tramp::BorrowRec<#lifetime, #inner_output>);
+    for param in inner_sig.generics.params.iter_mut() {
+        match param {
+            GenericParam::Type(param) => {
+                param.bounds.push(TypeParamBound::Lifetime(lifetime.clone()))
+            },
+            GenericParam::Lifetime(param) => {
+                param.bounds.push(lifetime.clone())
+            },
+            GenericParam::Const(_) => (),
+        }
+    }
+    inner_sig.generics.params.insert(0, parse_quote!(#lifetime));
+
+    let mut body = *block;
+    let mut rewriter = Rewriter {
+        name: &sig.ident,
+        try_kind,
+        closures: 0,
+        errors: Vec::new(),
+    };
+    rewriter.tail_block(&mut body);
+    if let Some(error) = rewriter.errors.into_iter().reduce(|mut all, error| {
+        all.combine(error);
+        all
+    }) {
+        return Err(error);
+    }
+
+    let name = &sig.ident;
+    Ok(quote! {
+        #(#attrs)*
+        #vis #outer_sig {
+            #[allow(unreachable_code)]
+            #inner_sig #body
+
+            ::tramp::tramp(#name(#(#args),*))
+        }
+    })
+}
+
+/// Rejects the kinds of functions which cannot be trampolined.
+fn check_sig(sig: &Signature, what: &str) -> syn::Result<()> {
+    if let Some(token) = &sig.constness {
+        return Err(Error::new(
+            token.span,
+            format!("{} cannot be used on a `const fn`", what),
+        ));
+    }
+    if let Some(token) = &sig.asyncness {
+        return Err(Error::new(
+            token.span,
+            format!(
+                "{} cannot be used on an `async fn`, use `AsyncRec` instead",
+                what
+            ),
+        ));
+    }
+    if let Some(variadic) = &sig.variadic {
+        return Err(Error::new_spanned(
+            variadic,
+            format!("{} cannot be used on a variadic function", what),
+        ));
+    }
+    if let Some(FnArg::Receiver(receiver)) = sig.inputs.first() {
+        return Err(Error::new_spanned(
+            receiver,
+            format!("{} cannot be used on a method", what),
+        ));
+    }
+    Ok(())
+}
+
+/// The signature of the public wrapper of a function, which takes every
+/// argument by a plain name, whatever the pattern, along with these names.
+fn outer_sig(sig: &Signature) -> (Signature, Vec<Ident>) {
+    let mut outer_sig = sig.clone();
+    let mut args = Vec::new();
+    for (i, input) in outer_sig.inputs.iter_mut().enumerate() {
+        if let FnArg::Typed(arg) = input {
+            let ident = format_ident!("__tramp_arg{}", i);
+            *arg.pat = parse_quote!(#ident);
+            args.push(ident);
+        }
+    }
+    (outer_sig, args)
+}
+
+/// The return type of a function.
+fn output(sig: &Signature) -> Type {
+    match &sig.output {
+        ReturnType::Default => parse_quote!(()),
+        ReturnType::Type(_, ty) => (**ty).clone(),
+    }
+}
+
+/// Whether a statement never completes, e.g. `return x;`, so that nothing
+/// needs to be returned after it.
+fn diverges(stmt: &Stmt) -> bool {
+    let mac = match stmt {
+        Stmt::Expr(Expr::Return(_), _)
+        | Stmt::Expr(Expr::Break(_), _)
+        | Stmt::Expr(Expr::Continue(_), _) => return true,
+        Stmt::Expr(Expr::Macro(expr), _) => &expr.mac,
+        Stmt::Macro(stmt) => &stmt.mac,
+        _ => return false,
+    };
+    mac.path.segments.last().is_some_and(|segment| {
+        ["panic", "unreachable", "todo", "unimplemented"]
+            .iter()
+            .any(|name| segment.ident == name)
+    })
+}
+
+/// How the `?` operator is rewritten in the inner function, depending on
+/// the return type of the function.
+#[derive(Clone, Copy)]
+enum TryKind {
+    Result,
+    Option,
+    /// `?` is left alone, which the compiler rejects.
+    Other,
+}
+
+impl TryKind {
+    fn of(output: &Type) -> Self {
+        match output {
+            Type::Path(path) => match path.path.segments.last() {
+                Some(segment) if segment.ident == "Result" => TryKind::Result,
+                Some(segment) if segment.ident == "Option" => TryKind::Option,
+                _ => TryKind::Other,
+            },
+            _ => TryKind::Other,
+        }
+    }
+}
+
+/// Names the elided lifetimes of a type with the given lifetime, and bounds
+/// `impl Trait`s by it. Elided lifetimes of `Fn` sugar and function pointers
+/// are higher-ranked, so they are left alone. An `impl Trait` behind a
+/// reference is not bounded, since it outlives the reference anyway, and
+/// `&impl Trait + 'a` would not parse.
+struct Elided<'l> {
+    lifetime: &'l Lifetime,
+    /// Whether the lifetime was put in any of the visited types.
+    used: bool,
+    /// How many references the visited type is behind.
+    references: usize,
+}
+
+impl<'l> Elided<'l> {
+    fn new(lifetime: &'l Lifetime) -> Self {
+        Self {
+            lifetime,
+            used: false,
+            references: 0,
+        }
+    }
+}
+
+impl VisitMut for Elided<'_> {
+    fn visit_type_reference_mut(&mut self, ty: &mut TypeReference) {
+        if ty.lifetime.is_none() {
+            ty.lifetime = Some(self.lifetime.clone());
+            self.used = true;
+        }
+        self.references += 1;
+        visit_mut::visit_type_reference_mut(self, ty);
+        self.references -= 1;
+    }
+
+    fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+        if lifetime.ident == "_" {
+            *lifetime = self.lifetime.clone();
+            self.used = true;
+        }
+    }
+
+    fn visit_type_impl_trait_mut(&mut self, ty: &mut TypeImplTrait) {
+        visit_mut::visit_type_impl_trait_mut(self, ty);
+        if self.references == 0 {
+            ty.bounds.push(TypeParamBound::Lifetime(self.lifetime.clone()));
+            self.used = true;
+        }
+    }
+
+    fn visit_type_bare_fn_mut(&mut self, _: &mut TypeBareFn) {}
+
+    fn visit_parenthesized_generic_arguments_mut(
+        &mut self,
+        _: &mut ParenthesizedGenericArguments,
+    ) {
+    }
+}
+
+/// Rewrites the body of the function into the body of the inner function.
+/// Expressions in tail position are handled by `tail_expr`, everything else
+/// by the `VisitMut` implementation.
+struct Rewriter<'n> {
+    name: &'n Ident,
+    try_kind: TryKind,
+    /// How many closures or async blocks are being visited. `return` and
+    /// `?` in them do not return from the function.
+    closures: usize,
+    errors: Vec<Error>,
+}
+
+impl Rewriter<'_> {
+    fn is_self_call(&self, call: &ExprCall) -> bool {
+        match &*call.func {
+            Expr::Path(path) => {
+                path.qself.is_none()
+                    && path.path.leading_colon.is_none()
+                    && path.path.segments.len() == 1
+                    && path.path.segments[0].ident == *self.name
+            },
+            _ => false,
+        }
+    }
+
+    fn tail_block(&mut self, block: &mut Block) {
+        let len = block.stmts.len();
+        for stmt in &mut block.stmts[..len.saturating_sub(1)] {
+            self.visit_stmt_mut(stmt);
+        }
+        match block.stmts.last_mut() {
+            Some(Stmt::Expr(expr, None)) => self.tail_expr(expr),
+            last => {
+                if let Some(stmt) = last {
+                    self.visit_stmt_mut(stmt);
+                    if diverges(stmt) {
+                        return;
+                    }
+                }
+                block.stmts.push(Stmt::Expr(
+                    parse_quote!(::tramp::BorrowRec::Ret(())),
+                    None,
+                ));
+            },
+        }
+    }
+
+    fn tail_expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Block(block) if block.label.is_none() => {
+                self.tail_block(&mut block.block)
+            },
+            Expr::Unsafe(block) => self.tail_block(&mut block.block),
+            Expr::Paren(paren) => self.tail_expr(&mut paren.expr),
+            Expr::If(cond) => {
+                self.visit_expr_mut(&mut cond.cond);
+                self.tail_block(&mut cond.then_branch);
+                match &mut cond.else_branch {
+                    Some((_, other)) => self.tail_expr(other),
+                    None => {
+                        cond.else_branch = Some((
+                            Default::default(),
+                            parse_quote!({ ::tramp::BorrowRec::Ret(()) }),
+                        ))
+                    },
+                }
+            },
+            Expr::Match(matching) => {
+                self.visit_expr_mut(&mut matching.expr);
+                for arm in &mut matching.arms {
+                    if let Some((_, guard)) = &mut arm.guard {
+                        self.visit_expr_mut(guard);
+                    }
+                    self.tail_expr(&mut arm.body);
+                }
+            },
+            Expr::Call(call) if self.is_self_call(call) => {
+                for arg in &mut call.args {
+                    self.visit_expr_mut(arg);
+                }
+                *expr = Self::defer(call);
+            },
+            Expr::Return(_) => self.visit_expr_mut(expr),
+            _ => {
+                self.visit_expr_mut(expr);
+                *expr = parse_quote_spanned!(expr.span()=>
+                    ::tramp::BorrowRec::Ret(#expr)
+                );
+            },
+        }
+    }
+
+    /// Turns a call in tail position into a `BorrowRec::Call` labeled with
+    /// the name of the function, evaluating its arguments first.
+    fn defer(call: &ExprCall) -> Expr {
+        let func = &call.func;
+        let label = quote!(#func).to_string();
+        let bindings = (0..call.args.len())
+            .map(|i| format_ident!("__tramp_arg{}", i))
+            .collect::<Vec<_>>();
+        let args = call.args.iter();
+        parse_quote_spanned!(call.span()=> {
+            #(let #bindings = #args;)*
+            ::tramp::BorrowRec::Call(
+                ::tramp::Thunk::new(move || #func(#(#bindings),*))
+                    .with_label(#label),
+            )
+        })
+    }
+
+    fn rewrite_try(&self, expr: &ExprTry) -> Option<Expr> {
+        let inner = &expr.expr;
+        match self.try_kind {
+            TryKind::Result => Some(parse_quote!(
+                match #inner {
+                    ::core::result::Result::Ok(__tramp_ok) => __tramp_ok,
+                    ::core::result::Result::Err(__tramp_err) => {
+                        return ::tramp::BorrowRec::Ret(
+                            ::core::result::Result::Err(
+                                ::core::convert::From::from(__tramp_err),
+                            ),
+                        );
+                    },
+                }
+            )),
+            TryKind::Option => Some(parse_quote!(
+                match #inner {
+                    ::core::option::Option::Some(__tramp_some) => __tramp_some,
+                    ::core::option::Option::None => {
+                        return ::tramp::BorrowRec::Ret(
+                            ::core::option::Option::None,
+                        );
+                    },
+                }
+            )),
+            TryKind::Other => None,
+        }
+    }
+}
+
+impl VisitMut for Rewriter<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Return(ret) if self.closures == 0 => match &mut ret.expr {
+                Some(value) => self.tail_expr(value),
+                None => {
+                    ret.expr = Some(Box::new(parse_quote!(
+                        ::tramp::BorrowRec::Ret(())
+                    )))
+                },
+            },
+            Expr::Try(question) if self.closures == 0 => {
+                self.visit_expr_mut(&mut question.expr);
+                if let Some(rewritten) = self.rewrite_try(question) {
+                    *expr = rewritten;
+                }
+            },
+            Expr::Closure(_) | Expr::Async(_) => {
+                self.closures += 1;
+                visit_mut::visit_expr_mut(self, expr);
+                self.closures -= 1;
+            },
+            Expr::Call(call) if self.is_self_call(call) => {
+                self.errors.push(Error::new_spanned(
+                    &*call,
+                    format!(
+                        "recursive call to `{}` is not in tail position",
+                        self.name
+                    ),
+                ));
+                visit_mut::visit_expr_call_mut(self, call);
+            },
+            _ => visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+
+    fn visit_item_mut(&mut self, _: &mut Item) {}
+}
+
@@ -0,0 +1,197 @@
+use crate::{tramp, BorrowRec, Thunk, Trampoline};
+use alloc::{
+    alloc::{alloc, dealloc, handle_alloc_error, Layout},
+    vec::Vec,
+};
+use core::{cell::Cell, cell::RefCell, cmp, fmt, ptr::NonNull};
+
+/// Size of the first chunk allocated by an arena.
+const MIN_CHUNK: usize = 4096;
+
+/// Alignment of the chunks allocated by an arena.
+const CHUNK_ALIGN: usize = 16;
+
+/// A bump arena holding the thunks of trampolined computations. Thunks
+/// created through a [`Scope`] of the arena store their closures in it
+/// (unless they are small enough to be stored inline), and the memory is
+/// given back all at once when the arena is reset or dropped.
+///
+/// Memory is never reused before that, so an arena is meant for short-lived
+/// computations: every thunk of a run takes up space until the run is over.
+pub struct Arena {
+    chunks: RefCell<Vec<(NonNull<u8>, usize)>>,
+    used: Cell<usize>,
+    allocated: Cell<usize>,
+}
+
+// The arena only owns raw memory; whatever lives in it borrows the arena,
+// so it cannot be sent to another thread while anything is still inside.
+unsafe impl Send for Arena {}
+
+impl Arena {
+    /// Creates an empty arena. No memory is allocated until a thunk is
+    /// stored in it.
+    pub fn new() -> Self {
+        Self {
+            chunks: RefCell::new(Vec::new()),
+            used: Cell::new(0),
+            allocated: Cell::new(0),
+        }
+    }
+
+    /// Creates a scope for creating thunks in this arena.
+    pub fn scope(&self) -> Scope<'_> {
+        Scope { arena: self }
+    }
+
+    /// Number of bytes handed out by this arena since it was created or
+    /// last reset.
+    pub fn allocated(&self) -> usize {
+        self.allocated.get()
+    }
+
+    /// Frees everything stored in this arena at once. The biggest chunk is
+    /// kept so that the next run can use it without allocating.
+    pub fn reset(&mut self) {
+        let chunks = self.chunks.get_mut();
+        if let Some(last) = chunks.pop() {
+            for (ptr, size) in chunks.drain(..) {
+                unsafe { dealloc(ptr.as_ptr(), chunk_layout(size)) };
+            }
+            chunks.push(last);
+        }
+        self.used.set(0);
+        self.allocated.set(0);
+    }
+
+    /// Bumps the arena, returning a pointer to memory fitting `layout`.
+    pub(crate) fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.allocated.set(self.allocated.get() + layout.size());
+        let mut chunks = self.chunks.borrow_mut();
+        if let Some(&(base, size)) = chunks.last() {
+            let start = self.used.get();
+            let pad = unsafe { base.as_ptr().add(start) }
+                .align_offset(layout.align());
+            let end = start
+                .checked_add(pad)
+                .and_then(|offset| offset.checked_add(layout.size()));
+            if let Some(end) = end.filter(|&end| end <= size) {
+                self.used.set(end);
+                return unsafe { base.as_ptr().add(start + pad) };
+            }
+        }
+
+        let size = chunks
+            .last()
+            .map_or(MIN_CHUNK, |&(_, size)| size * 2)
+            .max(layout.size() + cmp::max(layout.align(), CHUNK_ALIGN));
+        let chunk_layout = chunk_layout(size);
+        let base = match NonNull::new(unsafe { alloc(chunk_layout) }) {
+            Some(base) => base,
+            None => handle_alloc_error(chunk_layout),
+        };
+        chunks.push((base, size));
+        let pad = base.as_ptr().align_offset(layout.align());
+        self.used.set(pad + layout.size());
+        unsafe { base.as_ptr().add(pad) }
+    }
+}
+
+fn chunk_layout(size: usize) -> Layout {
+    Layout::from_size_align(size, CHUNK_ALIGN).expect("arena chunk too big")
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        for &(ptr, size) in self.chunks.get_mut().iter() {
+            unsafe { dealloc(ptr.as_ptr(), chunk_layout(size)) };
+        }
+    }
+}
+
+impl fmt::Debug for Arena {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.debug_struct("Arena")
+            .field("allocated", &self.allocated.get())
+            .finish()
+    }
+}
+
+/// A handle for creating thunks in an [`Arena`]. The lifetime of the thunks
+/// and of the `BorrowRec`s built with them is tied to the arena, so they
+/// cannot escape it.
+#[derive(Debug, Clone, Copy)]
+pub struct Scope<'a> {
+    arena: &'a Arena,
+}
+
+impl<'a> Scope<'a> {
+    /// The arena thunks are created in.
+    pub fn arena(self) -> &'a Arena {
+        self.arena
+    }
+
+    /// Creates a new thunk in the arena of this scope.
+    #[track_caller]
+    pub fn thunk<T, F>(self, fun: F) -> Thunk<'a, T>
+    where
+        F: FnOnce() -> T + 'a,
+    {
+        Thunk::new_in(self.arena, fun)
+    }
+
+    /// Creates a `BorrowRec::Call` whose thunk is in the arena of this
+    /// scope. This is what `rec_call!(in scope => ...)` expands to.
+    #[track_caller]
+    pub fn call<T, F>(self, fun: F) -> BorrowRec<'a, T>
+    where
+        F: FnOnce() -> BorrowRec<'a, T> + 'a,
+    {
+        BorrowRec::Call(self.thunk(fun))
+    }
+}
+
+/// Performs a trampoline over the computation, e.g. a `BorrowRec`, built by
+/// `start` with a scope of the given arena. Thunks created through the scope live in the arena,
+/// which can be reset after the run to free them all at once.
+///
+/// ```rust
+/// #[macro_use] extern crate tramp;
+///
+/// use tramp::{tramp_in, Arena, BorrowRec, Scope};
+///
+/// fn sum<'a>(scope: Scope<'a>, xs: &'a [u64], acc: u64) -> BorrowRec<'a, u64> {
+///     match xs.split_first() {
+///         Some((x, rest)) => rec_call!(in scope => sum(scope, rest, acc + x)),
+///         None => rec_ret!(acc),
+///     }
+/// }
+///
+/// let xs: Vec<u64> = (1..=100).collect();
+/// let mut arena = Arena::new();
+/// assert_eq!(tramp_in(&arena, |scope| sum(scope, &xs, 0)), 5050);
+/// arena.reset();
+/// ```
+pub fn tramp_in<'a, R, F>(arena: &'a Arena, start: F) -> R::Output
+where
+    R: Trampoline,
+    F: FnOnce(Scope<'a>) -> R,
+{
+    tramp(start(arena.scope()))
+}
+
+/// Performs a trampoline over the `BorrowRec` built by `start` with a scope
+/// of a fresh arena, which is freed when the trampoline returns.
+pub fn tramp_scoped<T, F>(start: F) -> T
+where
+    F: for<'a> FnOnce(Scope<'a>) -> BorrowRec<'a, T>,
+{
+    let arena = Arena::new();
+    tramp_in(&arena, start)
+}
@@ -0,0 +1,162 @@
+use alloc::boxed::Box;
+use core::{fmt, future::Future, pin::Pin};
+
+/// A single async recursive-function result. This mirrors `BorrowRec`, but
+/// the next step of the recursion is a future, so the functions of the
+/// recursion can be `async fn`s. See `SendAsyncRec` for recursions which
+/// run on multi-threaded executors.
+pub enum AsyncRec<'a, T> {
+    /// This variant is returned when the function is done; i.e. this the
+    /// result of the computation.
+    Ret(T),
+    /// This variant is returned when the function is about to call itself
+    /// or another in a tail position. The future is the call, producing the
+    /// next step of the recursion.
+    Call(Pin<Box<dyn Future<Output = AsyncRec<'a, T>> + 'a>>),
+}
+
+impl<'a, T> AsyncRec<'a, T> {
+    /// Creates a `AsyncRec::Call` from the given future, which is probably
+    /// the call of an `async fn`. This is what `async_rec_call!` expands
+    /// to.
+    pub fn call<F>(future: F) -> Self
+    where
+        F: Future<Output = Self> + 'a,
+    {
+        AsyncRec::Call(Box::pin(future))
+    }
+}
+
+impl<'a, T> fmt::Debug for AsyncRec<'a, T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsyncRec::Ret(x) => fmtr.debug_tuple("Ret").field(x).finish(),
+            AsyncRec::Call(_) => fmtr.debug_tuple("Call").finish_non_exhaustive(),
+        }
+    }
+}
+
+/// Given an input of type `AsyncRec`, this function performs a trampoline
+/// over the value, awaiting the calls one after the other. Since every call
+/// is awaited from here instead of from the function which made it, neither
+/// the native stack nor the depth of nested polls grows with the recursion.
+///
+/// ```rust
+/// #[macro_use] extern crate tramp;
+///
+/// use tramp::{atramp, AsyncRec};
+///
+/// async fn count(n: u64, acc: u64) -> AsyncRec<'static, u64> {
+///     if n > 0 {
+///         async_rec_call!(count(n - 1, acc + 1))
+///     } else {
+///         async_rec_ret!(acc)
+///     }
+/// }
+///
+/// async fn total() -> u64 {
+///     atramp(count(10, 0).await).await
+/// }
+/// ```
+pub async fn atramp<'a, T>(mut res: AsyncRec<'a, T>) -> T {
+    loop {
+        match res {
+            AsyncRec::Ret(x) => break x,
+            AsyncRec::Call(future) => res = future.await,
+        }
+    }
+}
+
+/// A single async recursive-function result whose calls are `Send`
+/// futures, so that `atramp_send` returns a `Send` future, which can be
+/// spawned on a multi-threaded executor. The calls are made with
+/// `send_async_rec_call!`. The compiler cannot tell whether the future of a
+/// recursive `async fn` is `Send`, so the functions of the recursion return
+/// an `impl Future + Send` instead, made of an `async` block.
+///
+/// ```rust
+/// #[macro_use] extern crate tramp;
+///
+/// use std::future::Future;
+/// use tramp::{atramp_send, SendAsyncRec};
+///
+/// fn count(
+///     n: u64,
+///     acc: u64,
+/// ) -> impl Future<Output = SendAsyncRec<'static, u64>> + Send {
+///     async move {
+///         if n > 0 {
+///             send_async_rec_call!(count(n - 1, acc + 1))
+///         } else {
+///             send_async_rec_ret!(acc)
+///         }
+///     }
+/// }
+///
+/// fn total() -> impl Future<Output = u64> + Send {
+///     atramp_send(SendAsyncRec::call(count(10, 0)))
+/// }
+/// ```
+pub enum SendAsyncRec<'a, T> {
+    /// This variant is returned when the function is done; i.e. this the
+    /// result of the computation.
+    Ret(T),
+    /// This variant is returned when the function is about to call itself
+    /// or another in a tail position. The future is the call, producing the
+    /// next step of the recursion.
+    Call(Pin<Box<dyn Future<Output = SendAsyncRec<'a, T>> + Send + 'a>>),
+}
+
+impl<'a, T> SendAsyncRec<'a, T> {
+    /// Creates a `SendAsyncRec::Call` from the given `Send` future. This is
+    /// what `send_async_rec_call!` expands to.
+    pub fn call<F>(future: F) -> Self
+    where
+        F: Future<Output = Self> + Send + 'a,
+    {
+        SendAsyncRec::Call(Box::pin(future))
+    }
+}
+
+impl<'a, T> fmt::Debug for SendAsyncRec<'a, T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SendAsyncRec::Ret(x) => fmtr.debug_tuple("Ret").field(x).finish(),
+            SendAsyncRec::Call(_) => {
+                fmtr.debug_tuple("Call").finish_non_exhaustive()
+            },
+        }
+    }
+}
+
+/// Forgets that the calls are `Send`.
+impl<'a, T> From<SendAsyncRec<'a, T>> for AsyncRec<'a, T>
+where
+    T: 'a,
+{
+    fn from(res: SendAsyncRec<'a, T>) -> Self {
+        match res {
+            SendAsyncRec::Ret(x) => AsyncRec::Ret(x),
+            SendAsyncRec::Call(future) => {
+                AsyncRec::call(async move { future.await.into() })
+            },
+        }
+    }
+}
+
+/// Performs a trampoline over the `SendAsyncRec`, like `atramp`. The future
+/// returned is `Send` as long as the result is.
+pub async fn atramp_send<'a, T>(mut res: SendAsyncRec<'a, T>) -> T {
+    loop {
+        match res {
+            SendAsyncRec::Ret(x) => break x,
+            SendAsyncRec::Call(future) => res = future.await,
+        }
+    }
+}
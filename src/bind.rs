@@ -0,0 +1,154 @@
+use crate::{BorrowRec, CallSite, Thunk};
+use alloc::{boxed::Box, collections::VecDeque, rc::Rc};
+use core::{cell::Cell, fmt, mem};
+
+/// A slot the result of a sub-computation is written to, so that the
+/// continuation waiting for it can pick it up. This lets computations of
+/// different result types share one continuation stack.
+type Slot<T> = Rc<Cell<Option<T>>>;
+
+/// A computation whose result type was erased; its result is left in the
+/// slot it was lifted with.
+enum Node<'a> {
+    /// The computation is done and its result is in its slot.
+    Done,
+    /// The computation has to compute the thunk to make progress.
+    Call(Thunk<'a, Node<'a>>),
+    /// The computation is another frame, whose continuations run before the
+    /// ones of the current frame.
+    Bind(Box<Frame<'a>>),
+}
+
+/// A computation followed by the continuations to run after it, in order.
+struct Frame<'a> {
+    node: Node<'a>,
+    conts: VecDeque<Thunk<'a, Node<'a>>>,
+}
+
+/// A computation with continuations, i.e. the state of a `BorrowRec` built
+/// with `BorrowRec::and_then` or `BorrowRec::map`. The continuations are
+/// kept on a heap-allocated stack, so running them takes constant native
+/// stack however deep the recursion is.
+pub struct Bind<'a, T> {
+    frame: Frame<'a>,
+    slot: Slot<T>,
+}
+
+/// Erases the result type of a computation, which will write its result to
+/// `out` when done.
+fn lift<'a, T>(rec: BorrowRec<'a, T>, out: Slot<T>) -> Node<'a>
+where
+    T: 'a,
+{
+    match rec {
+        BorrowRec::Ret(x) => {
+            out.set(Some(x));
+            Node::Done
+        }
+        BorrowRec::Call(thunk) => {
+            Node::Call(thunk.map(move |rec| lift(rec, out)))
+        }
+        BorrowRec::Bind(Bind { mut frame, slot }) => {
+            frame.conts.push_back(Thunk::new(move || {
+                out.set(slot.take());
+                Node::Done
+            }));
+            Node::Bind(Box::new(frame))
+        }
+    }
+}
+
+/// Sequences `rec` and `fun`; see `BorrowRec::and_then`.
+pub(crate) fn bind<'a, T, U, F>(
+    rec: BorrowRec<'a, T>,
+    fun: F,
+) -> BorrowRec<'a, U>
+where
+    T: 'a,
+    U: 'a,
+    F: FnOnce(T) -> BorrowRec<'a, U> + 'a,
+{
+    let (mut frame, slot) = match rec {
+        BorrowRec::Ret(x) => return fun(x),
+        BorrowRec::Call(thunk) => {
+            let slot = Rc::new(Cell::new(None));
+            let node = lift(BorrowRec::Call(thunk), slot.clone());
+            (
+                Frame {
+                    node,
+                    conts: VecDeque::new(),
+                },
+                slot,
+            )
+        }
+        BorrowRec::Bind(Bind { frame, slot }) => (frame, slot),
+    };
+    let out = Rc::new(Cell::new(None));
+    let cont_out = out.clone();
+    frame.conts.push_back(Thunk::new(move || {
+        let x = slot.take().expect("computation finished without result");
+        lift(fun(x), cont_out)
+    }));
+    BorrowRec::Bind(Bind { frame, slot: out })
+}
+
+impl<'a, T> Bind<'a, T> {
+    /// Runs one step of this computation: computes a thunk, or runs a
+    /// continuation. Returns `BorrowRec::Ret` once all of them ran.
+    pub(crate) fn step(mut self) -> BorrowRec<'a, T> {
+        match mem::replace(&mut self.frame.node, Node::Done) {
+            Node::Done => match self.frame.conts.pop_front() {
+                Some(cont) => self.frame.node = cont.compute(),
+                None => {
+                    let x = self
+                        .slot
+                        .take()
+                        .expect("computation finished without result");
+                    return BorrowRec::Ret(x);
+                }
+            },
+            Node::Call(thunk) => self.frame.node = thunk.compute(),
+            Node::Bind(inner) => {
+                let Frame { node, conts } = *inner;
+                for cont in conts.into_iter().rev() {
+                    self.frame.conts.push_front(cont);
+                }
+                self.frame.node = node;
+            }
+        }
+        BorrowRec::Bind(self)
+    }
+
+    /// How many continuations are waiting, including the ones of the nested
+    /// frames.
+    pub(crate) fn pending(&self) -> usize {
+        let mut pending = self.frame.conts.len();
+        let mut node = &self.frame.node;
+        while let Node::Bind(inner) = node {
+            pending += inner.conts.len();
+            node = &inner.node;
+        }
+        pending
+    }
+
+    /// Whether the next step runs a continuation.
+    pub(crate) fn runs_continuation(&self) -> bool {
+        matches!(self.frame.node, Node::Done) && !self.frame.conts.is_empty()
+    }
+
+    /// The call this computation is about to make, if its next step is one.
+    pub(crate) fn call_site(&self) -> Option<CallSite> {
+        match &self.frame.node {
+            Node::Call(thunk) => Some(CallSite::of(thunk)),
+            _ => None,
+        }
+    }
+}
+
+impl<'a, T> fmt::Debug for Bind<'a, T> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.debug_struct("Bind")
+            .field("continuations", &self.frame.conts.len())
+            .finish_non_exhaustive()
+    }
+}
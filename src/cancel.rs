@@ -0,0 +1,90 @@
+use crate::{tramp_with_fuel, Trampoline};
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+/// A flag used to cancel trampolines from outside, e.g. from another thread
+/// (wrapped in an `Arc`) or from a signal handler.
+#[derive(Debug, Default)]
+pub struct CancelToken {
+    cancelled: AtomicBool,
+}
+
+impl CancelToken {
+    /// Creates a token which is not cancelled.
+    pub const fn new() -> Self {
+        Self {
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    /// Requests the cancellation of the trampolines checking this token.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the cancellation was requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Clears a cancellation request, so the token can be reused.
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Like `tramp`, but checks the given token every `interval` steps. If the
+/// cancellation was requested, the computation is suspended and returned
+/// in `Err`, so the caller may either drop it or resume it later.
+///
+/// ```rust
+/// #[macro_use] extern crate tramp;
+///
+/// use tramp::{tramp_cancellable, CancelToken, Rec};
+///
+/// fn spin(token: &'static CancelToken, n: u64) -> Rec<u64> {
+///     if n == 5000 {
+///         token.cancel();
+///     }
+///     rec_call!(spin(token, n + 1))
+/// }
+///
+/// static TOKEN: CancelToken = CancelToken::new();
+/// assert!(tramp_cancellable(spin(&TOKEN, 0), &TOKEN, 1000).is_err());
+/// ```
+pub fn tramp_cancellable<R: Trampoline>(
+    mut res: R,
+    token: &CancelToken,
+    interval: usize,
+) -> Result<R::Output, R> {
+    loop {
+        if token.is_cancelled() {
+            break Err(res);
+        }
+        match tramp_with_fuel(res, interval.max(1)) {
+            Ok(x) => break Ok(x),
+            Err(suspended) => res = suspended,
+        }
+    }
+}
+
+/// Like `tramp`, but checks the time every `interval` steps. If the
+/// deadline has passed, the computation is suspended and returned in `Err`,
+/// so the caller may either drop it or resume it later.
+#[cfg(feature = "std")]
+pub fn tramp_until<R: Trampoline>(
+    mut res: R,
+    deadline: Instant,
+    interval: usize,
+) -> Result<R::Output, R> {
+    loop {
+        if Instant::now() >= deadline {
+            break Err(res);
+        }
+        match tramp_with_fuel(res, interval.max(1)) {
+            Ok(x) => break Ok(x),
+            Err(suspended) => res = suspended,
+        }
+    }
+}
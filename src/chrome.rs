@@ -0,0 +1,216 @@
+use crate::{tramp_observed, BorrowRec, CallSite, Observer};
+use core::{ops::ControlFlow, time::Duration};
+use std::{
+    format,
+    io::{self, Write},
+    time::Instant,
+    vec::Vec,
+};
+
+/// A recorded step of a trampoline.
+#[derive(Debug, Clone, Copy)]
+struct Event {
+    call: Option<CallSite>,
+    step: u64,
+    start: Duration,
+    duration: Duration,
+}
+
+/// Records the steps of a trampoline with their timestamps, to be written
+/// in the Chrome Trace Event format and loaded in `chrome://tracing` or
+/// Perfetto. Calls are named after the labels of their thunks, recorded
+/// with the `debug-info` feature.
+///
+/// Only one step out of `sample_every` is recorded, and at most `limit`
+/// steps, so that long runs still give small traces.
+///
+/// ```rust
+/// #[macro_use] extern crate tramp;
+///
+/// use tramp::{ChromeTrace, Rec};
+///
+/// fn count(n: u64, acc: u64) -> Rec<u64> {
+///     if n > 0 {
+///         rec_call!(count(n - 1, acc + 1))
+///     } else {
+///         rec_ret!(acc)
+///     }
+/// }
+///
+/// let mut trace = ChromeTrace::new().sample_every(100).limit(50);
+/// assert_eq!(trace.run(count(10000, 0)), 10000);
+/// assert_eq!(trace.len(), 50);
+///
+/// let mut json = Vec::new();
+/// trace.write_json(&mut json).unwrap();
+/// assert!(json.starts_with(b"{\"traceEvents\":["));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChromeTrace {
+    origin: Instant,
+    events: Vec<Event>,
+    sample_every: u64,
+    limit: usize,
+    steps: u64,
+    dropped: u64,
+    /// The step being timed, if it is sampled, and when it started.
+    pending: Option<(Event, Instant)>,
+}
+
+impl Default for ChromeTrace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChromeTrace {
+    /// Creates a recorder keeping every step, without limit.
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            events: Vec::new(),
+            sample_every: 1,
+            limit: usize::MAX,
+            steps: 0,
+            dropped: 0,
+            pending: None,
+        }
+    }
+
+    /// Records only one step out of `steps`.
+    pub fn sample_every(self, steps: u64) -> Self {
+        Self {
+            sample_every: steps.max(1),
+            ..self
+        }
+    }
+
+    /// Records at most `events` steps; the sampled steps after them are
+    /// only counted.
+    pub fn limit(self, events: usize) -> Self {
+        Self {
+            limit: events,
+            ..self
+        }
+    }
+
+    /// Runs the trampoline, recording its steps.
+    pub fn run<'a, T>(&mut self, res: BorrowRec<'a, T>) -> T {
+        match tramp_observed(res, self) {
+            Ok(x) => x,
+            Err(_) => unreachable!("ChromeTrace never stops a trampoline"),
+        }
+    }
+
+    /// How many steps were recorded.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Whether no step was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// How many sampled steps were not recorded because of the limit.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Times the previous step, if sampled, and starts timing the step of
+    /// `res`, if sampled.
+    fn start<T>(&mut self, res: &BorrowRec<T>) {
+        let now = self.finish();
+        let step = self.steps;
+        self.steps += 1;
+        if !step.is_multiple_of(self.sample_every) {
+            return;
+        }
+        if self.events.len() >= self.limit {
+            self.dropped += 1;
+            return;
+        }
+        let call = res.call_site();
+        let now = now.unwrap_or_else(Instant::now);
+        let event = Event {
+            call,
+            step,
+            start: now - self.origin,
+            duration: Duration::ZERO,
+        };
+        self.pending = Some((event, now));
+    }
+
+    /// Times the pending step, if any, returning the current time.
+    fn finish(&mut self) -> Option<Instant> {
+        let (mut event, start) = self.pending.take()?;
+        let now = Instant::now();
+        event.duration = now - start;
+        self.events.push(event);
+        Some(now)
+    }
+
+    /// Writes the recorded steps in the Chrome Trace Event JSON format.
+    pub fn write_json<W: Write>(&self, mut out: W) -> io::Result<()> {
+        out.write_all(b"{\"traceEvents\":[")?;
+        for (i, event) in self.events.iter().enumerate() {
+            if i > 0 {
+                out.write_all(b",")?;
+            }
+            out.write_all(b"\n{\"name\":")?;
+            let name = match event.call {
+                Some(call) => call.label().unwrap_or("call"),
+                None => "continuation",
+            };
+            write_json_str(&mut out, name)?;
+            write!(
+                out,
+                ",\"cat\":\"tramp\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\
+                 \"pid\":0,\"tid\":0,\"args\":{{\"step\":{}",
+                micros(event.start),
+                micros(event.duration),
+                event.step,
+            )?;
+            let location = event.call.and_then(|call| call.location());
+            if let Some(location) = location {
+                out.write_all(b",\"location\":")?;
+                write_json_str(&mut out, &format!("{}", location))?;
+            }
+            out.write_all(b"}}")?;
+        }
+        write!(
+            out,
+            "\n],\"otherData\":{{\"steps\":{},\"dropped\":{}}}}}",
+            self.steps, self.dropped,
+        )
+    }
+}
+
+impl<'a, T> Observer<BorrowRec<'a, T>> for ChromeTrace {
+    fn on_step(&mut self, res: &BorrowRec<'a, T>) -> ControlFlow<()> {
+        self.start(res);
+        ControlFlow::Continue(())
+    }
+
+    fn on_ret(&mut self, _result: &T) {
+        self.finish();
+    }
+}
+
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1e6
+}
+
+/// Writes a JSON string literal.
+fn write_json_str<W: Write>(out: &mut W, s: &str) -> io::Result<()> {
+    out.write_all(b"\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => out.write_all(b"\\\"")?,
+            '\\' => out.write_all(b"\\\\")?,
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+    out.write_all(b"\"")
+}
@@ -0,0 +1,98 @@
+use alloc::vec::Vec;
+
+/// A recursive data structure which can be dropped without recursion: its
+/// children are moved out of it before it is dropped, so that dropping it
+/// does not drop them recursively.
+pub trait DropChildren: Sized {
+    /// Moves the children of this value to `out`, e.g. by taking them out
+    /// of their `Box`es, leaving this value without children.
+    fn take_children(&mut self, out: &mut Vec<Self>);
+}
+
+/// Drops the descendants of `value`, leaving it without children. They are
+/// kept on a heap worklist while being torn down, so any depth of structure
+/// takes constant native stack. This is what `impl_iterative_drop!` calls
+/// from `Drop::drop`.
+pub fn drop_children<T: DropChildren>(value: &mut T) {
+    let mut work = Vec::new();
+    value.take_children(&mut work);
+    while let Some(mut child) = work.pop() {
+        child.take_children(&mut work);
+    }
+}
+
+/// Drops `value` and all its descendants, taking constant native stack.
+///
+/// ```rust
+/// use tramp::{drop_iteratively, DropChildren};
+///
+/// struct Node {
+///     next: Option<Box<Node>>,
+/// }
+///
+/// impl DropChildren for Node {
+///     fn take_children(&mut self, out: &mut Vec<Self>) {
+///         out.extend(self.next.take().map(|next| *next));
+///     }
+/// }
+///
+/// let mut list = Node { next: None };
+/// for _ in 0..1000000 {
+///     list = Node { next: Some(Box::new(list)) };
+/// }
+/// drop_iteratively(list);
+/// ```
+pub fn drop_iteratively<T: DropChildren>(mut value: T) {
+    drop_children(&mut value);
+}
+
+/// Implements `Drop` for a type implementing `DropChildren`, so that its
+/// values are dropped with `drop_children`, taking constant native stack.
+/// Generic types are given with their parameters first, without bounds,
+/// e.g. `impl_iterative_drop!(<T> List<T>)`.
+///
+/// ```rust
+/// #[macro_use] extern crate tramp;
+///
+/// use tramp::DropChildren;
+///
+/// enum Expr {
+///     Num(u64),
+///     Add(Box<Expr>, Box<Expr>),
+/// }
+///
+/// impl DropChildren for Expr {
+///     fn take_children(&mut self, out: &mut Vec<Self>) {
+///         if let Expr::Add(left, right) = self {
+///             let left = std::mem::replace(&mut **left, Expr::Num(0));
+///             let right = std::mem::replace(&mut **right, Expr::Num(0));
+///             out.extend([left, right]);
+///         }
+///     }
+/// }
+///
+/// impl_iterative_drop!(Expr);
+///
+/// let mut expr = Expr::Num(0);
+/// for i in 0..1000000 {
+///     expr = Expr::Add(Box::new(expr), Box::new(Expr::Num(i)));
+/// }
+/// drop(expr);
+/// ```
+#[macro_export]
+macro_rules! impl_iterative_drop {
+    (<$($param:tt),+> $ty:ty) => {
+        impl<$($param),+> Drop for $ty {
+            fn drop(&mut self) {
+                $crate::drop_children(self);
+            }
+        }
+    };
+    ($ty:ty) => {
+        impl Drop for $ty {
+            fn drop(&mut self) {
+                $crate::drop_children(self);
+            }
+        }
+    };
+}
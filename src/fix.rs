@@ -0,0 +1,163 @@
+use crate::{BorrowRec, Thunk};
+use alloc::rc::Rc;
+
+/// Makes a recursive function out of a closure, which is given itself as
+/// `this`. Calling `this(arg)` does not run the closure but returns a
+/// `BorrowRec::Call` step, so the recursion runs on the trampoline and the
+/// closure may capture its environment, unlike the `fn` items `rec_call!`
+/// needs.
+///
+/// ```rust
+/// use tramp::{fix, tramp, BorrowRec};
+///
+/// let step = 3;
+/// let count = fix(|this, (n, acc): (u64, u64)| {
+///     if n >= step {
+///         this((n - step, acc + 1))
+///     } else {
+///         BorrowRec::Ret(acc)
+///     }
+/// });
+/// assert_eq!(tramp(count((3000000, 0))), 1000000);
+///
+/// let fac = fix(|this, n: u128| {
+///     if n > 1 {
+///         this(n - 1).map(move |x| n * x)
+///     } else {
+///         BorrowRec::Ret(1)
+///     }
+/// });
+/// assert_eq!(tramp(fac(5)), 120);
+/// ```
+pub fn fix<'a, A, T, F>(fun: F) -> impl Fn(A) -> BorrowRec<'a, T> + Clone + 'a
+where
+    A: 'a,
+    T: 'a,
+    F: Fn(&dyn Fn(A) -> BorrowRec<'a, T>, A) -> BorrowRec<'a, T> + 'a,
+{
+    let fun = Rc::new(fun);
+    move |arg| call(&fun, arg)
+}
+
+fn call<'a, A, T, F>(fun: &Rc<F>, arg: A) -> BorrowRec<'a, T>
+where
+    A: 'a,
+    T: 'a,
+    F: Fn(&dyn Fn(A) -> BorrowRec<'a, T>, A) -> BorrowRec<'a, T> + 'a,
+{
+    let fun = fun.clone();
+    BorrowRec::Call(Thunk::new(move || fun(&|arg| call(&fun, arg), arg)))
+}
+
+/// Like `fix`, but makes two mutually recursive functions out of two
+/// closures, which are both given both functions.
+///
+/// ```rust
+/// use tramp::{fix2, tramp, BorrowRec};
+///
+/// let (is_even, is_odd) = fix2(
+///     |_, is_odd, n: u64| {
+///         if n > 0 {
+///             is_odd(n - 1)
+///         } else {
+///             BorrowRec::Ret(true)
+///         }
+///     },
+///     |is_even, _, n: u64| {
+///         if n > 0 {
+///             is_even(n - 1)
+///         } else {
+///             BorrowRec::Ret(false)
+///         }
+///     },
+/// );
+/// assert!(tramp(is_even(100000)));
+/// assert!(!tramp(is_odd(100000)));
+/// ```
+pub fn fix2<'a, A, B, T, F, G>(
+    first: F,
+    second: G,
+) -> (
+    impl Fn(A) -> BorrowRec<'a, T> + Clone + 'a,
+    impl Fn(B) -> BorrowRec<'a, T> + Clone + 'a,
+)
+where
+    A: 'a,
+    B: 'a,
+    T: 'a,
+    F: Fn(
+            &dyn Fn(A) -> BorrowRec<'a, T>,
+            &dyn Fn(B) -> BorrowRec<'a, T>,
+            A,
+        ) -> BorrowRec<'a, T>
+        + 'a,
+    G: Fn(
+            &dyn Fn(A) -> BorrowRec<'a, T>,
+            &dyn Fn(B) -> BorrowRec<'a, T>,
+            B,
+        ) -> BorrowRec<'a, T>
+        + 'a,
+{
+    let funs = Rc::new((first, second));
+    let first_funs = funs.clone();
+    (
+        move |arg| call_first(&first_funs, arg),
+        move |arg| call_second(&funs, arg),
+    )
+}
+
+fn call_first<'a, A, B, T, F, G>(funs: &Rc<(F, G)>, arg: A) -> BorrowRec<'a, T>
+where
+    A: 'a,
+    B: 'a,
+    T: 'a,
+    F: Fn(
+            &dyn Fn(A) -> BorrowRec<'a, T>,
+            &dyn Fn(B) -> BorrowRec<'a, T>,
+            A,
+        ) -> BorrowRec<'a, T>
+        + 'a,
+    G: Fn(
+            &dyn Fn(A) -> BorrowRec<'a, T>,
+            &dyn Fn(B) -> BorrowRec<'a, T>,
+            B,
+        ) -> BorrowRec<'a, T>
+        + 'a,
+{
+    let funs = funs.clone();
+    BorrowRec::Call(Thunk::new(move || {
+        (funs.0)(
+            &|arg| call_first(&funs, arg),
+            &|arg| call_second(&funs, arg),
+            arg,
+        )
+    }))
+}
+
+fn call_second<'a, A, B, T, F, G>(funs: &Rc<(F, G)>, arg: B) -> BorrowRec<'a, T>
+where
+    A: 'a,
+    B: 'a,
+    T: 'a,
+    F: Fn(
+            &dyn Fn(A) -> BorrowRec<'a, T>,
+            &dyn Fn(B) -> BorrowRec<'a, T>,
+            A,
+        ) -> BorrowRec<'a, T>
+        + 'a,
+    G: Fn(
+            &dyn Fn(A) -> BorrowRec<'a, T>,
+            &dyn Fn(B) -> BorrowRec<'a, T>,
+            B,
+        ) -> BorrowRec<'a, T>
+        + 'a,
+{
+    let funs = funs.clone();
+    BorrowRec::Call(Thunk::new(move || {
+        (funs.1)(
+            &|arg| call_first(&funs, arg),
+            &|arg| call_second(&funs, arg),
+            arg,
+        )
+    }))
+}
@@ -0,0 +1,69 @@
+use crate::{tramp_with_fuel, Trampoline};
+use core::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// A future performing a trampoline, e.g. over a `BorrowRec`, a slice at a
+/// time.
+/// Every poll computes at most a given number of thunks; if the computation
+/// is not done by then, the future wakes itself up and returns
+/// `Poll::Pending`, letting the executor run other tasks in between. It
+/// works with any executor.
+pub struct TrampFuture<R> {
+    res: Option<R>,
+    steps_per_poll: usize,
+}
+
+impl<R: Trampoline> TrampFuture<R> {
+    /// Creates a future running `steps_per_poll` steps of `res` per poll.
+    pub fn new(res: R, steps_per_poll: usize) -> Self {
+        Self {
+            res: Some(res),
+            steps_per_poll: steps_per_poll.max(1),
+        }
+    }
+}
+
+// The computation is never pinned in place: it is moved in and out of the
+// future on every poll.
+impl<R> Unpin for TrampFuture<R> {}
+
+impl<R: Trampoline> Future for TrampFuture<R> {
+    type Output = R::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<R::Output> {
+        let res = self.res.take().expect("TrampFuture polled after completion");
+        match tramp_with_fuel(res, self.steps_per_poll) {
+            Ok(x) => Poll::Ready(x),
+            Err(suspended) => {
+                self.res = Some(suspended);
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            },
+        }
+    }
+}
+
+impl<R> fmt::Debug for TrampFuture<R>
+where
+    R: fmt::Debug,
+{
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.debug_struct("TrampFuture")
+            .field("res", &self.res)
+            .field("steps_per_poll", &self.steps_per_poll)
+            .finish()
+    }
+}
+
+/// Turns a trampoline into a future yielding to the executor every
+/// `steps_per_poll` steps. See [`TrampFuture`].
+pub fn tramp_future<R: Trampoline>(
+    res: R,
+    steps_per_poll: usize,
+) -> TrampFuture<R> {
+    TrampFuture::new(res, steps_per_poll)
+}
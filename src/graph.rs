@@ -0,0 +1,181 @@
+use crate::{tramp_observed, BorrowRec, Observer};
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::{fmt, ops::ControlFlow};
+
+/// Name of the calls whose thunks have no label.
+const UNKNOWN: &str = "<unknown>";
+
+/// The dynamic call graph of trampolined recursions: which function called
+/// which, and how many times. Functions are named after the labels of the
+/// thunks of their calls, recorded with the `debug-info` feature, so the
+/// calls made with `rec_call!(f(...))` are attributed to `f`. A call is
+/// made by the function of the previous call, or, if made by a continuation
+/// of `BorrowRec::and_then` or `BorrowRec::map`, by the function which
+/// chained that continuation. Its `Display` output is the graph in the
+/// Graphviz DOT language. The function starting a run is not a thunk, so
+/// its first calls have no caller, unless it is given to `run_from`.
+///
+/// ```rust
+/// #[macro_use] extern crate tramp;
+///
+/// use tramp::{CallGraph, Rec};
+///
+/// fn is_even(x: u128) -> Rec<bool> {
+///     if x > 0 {
+///         rec_call!(is_odd(x - 1))
+///     } else {
+///         rec_ret!(true)
+///     }
+/// }
+///
+/// fn is_odd(x: u128) -> Rec<bool> {
+///     if x > 0 {
+///         rec_call!(is_even(x - 1))
+///     } else {
+///         rec_ret!(false)
+///     }
+/// }
+///
+/// let mut graph = CallGraph::new();
+/// assert!(graph.run(is_even(10)));
+/// println!("{}", graph);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CallGraph {
+    calls: BTreeMap<&'static str, u64>,
+    edges: BTreeMap<(&'static str, &'static str), u64>,
+    /// The function running, i.e. the caller of the next call.
+    caller: Option<&'static str>,
+    /// The function which chained each waiting continuation, the next one
+    /// to run last.
+    conts: Vec<Option<&'static str>>,
+}
+
+impl CallGraph {
+    /// Creates an empty graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs the trampoline, adding its calls to the graph.
+    pub fn run<'a, T>(&mut self, res: BorrowRec<'a, T>) -> T {
+        self.start(None);
+        self.tramp(res)
+    }
+
+    /// Runs the trampoline, adding its calls to the graph, with `caller` as
+    /// the function starting it, e.g. the one called to get `res`.
+    pub fn run_from<'a, T>(
+        &mut self,
+        caller: &'static str,
+        res: BorrowRec<'a, T>,
+    ) -> T {
+        self.start(Some(caller));
+        self.tramp(res)
+    }
+
+    fn start(&mut self, caller: Option<&'static str>) {
+        self.caller = caller;
+        self.conts.clear();
+    }
+
+    fn tramp<'a, T>(&mut self, res: BorrowRec<'a, T>) -> T {
+        match tramp_observed(res, self) {
+            Ok(x) => x,
+            Err(_) => unreachable!("CallGraph never stops a trampoline"),
+        }
+    }
+
+    /// How many times the given function was called.
+    pub fn calls(&self, callee: &str) -> u64 {
+        self.calls.get(callee).copied().unwrap_or(0)
+    }
+
+    /// How many times `caller` called `callee`.
+    pub fn edge(&self, caller: &str, callee: &str) -> u64 {
+        self.edges.get(&(caller, callee)).copied().unwrap_or(0)
+    }
+
+    /// All the edges of the graph, as `(caller, callee, count)`.
+    pub fn edges(
+        &self,
+    ) -> impl Iterator<Item = (&'static str, &'static str, u64)> + '_ {
+        self.edges
+            .iter()
+            .map(|(&(caller, callee), &count)| (caller, callee, count))
+    }
+
+    fn record<T>(&mut self, res: &BorrowRec<T>) {
+        if let BorrowRec::Bind(bind) = res {
+            // The continuations chained by the last step were chained by
+            // the function running it, and are the next ones to run.
+            let pending = bind.pending();
+            if pending > self.conts.len() {
+                self.conts.resize(pending, self.caller);
+            }
+            if bind.runs_continuation() {
+                self.caller = self.conts.pop().flatten();
+            }
+        }
+        if let Some(call) = res.call_site() {
+            let callee = call.label().unwrap_or(UNKNOWN);
+            *self.calls.entry(callee).or_insert(0) += 1;
+            if let Some(caller) = self.caller {
+                *self.edges.entry((caller, callee)).or_insert(0) += 1;
+            }
+            self.caller = Some(callee);
+        }
+    }
+}
+
+impl<'a, T> Observer<BorrowRec<'a, T>> for CallGraph {
+    fn on_step(&mut self, res: &BorrowRec<'a, T>) -> ControlFlow<()> {
+        self.record(res);
+        ControlFlow::Continue(())
+    }
+
+    fn on_ret(&mut self, _result: &T) {
+        self.start(None);
+    }
+}
+
+/// Writes a string escaped for a DOT quoted string.
+struct Escaped<'s>(&'s str);
+
+impl<'s> fmt::Display for Escaped<'s> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '"' => fmtr.write_str("\\\"")?,
+                '\\' => fmtr.write_str("\\\\")?,
+                '\n' => fmtr.write_str("\\n")?,
+                c => write!(fmtr, "{}", c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CallGraph {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(fmtr, "digraph calls {{")?;
+        for (&callee, &count) in &self.calls {
+            let callee = Escaped(callee);
+            writeln!(
+                fmtr,
+                "    \"{}\" [label=\"{} ({})\"];",
+                callee, callee, count,
+            )?;
+        }
+        for (&(caller, callee), &count) in &self.edges {
+            writeln!(
+                fmtr,
+                "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                Escaped(caller),
+                Escaped(callee),
+                count,
+            )?;
+        }
+        write!(fmtr, "}}")
+    }
+}
@@ -0,0 +1,332 @@
+use crate::{tramp, BorrowRec, Trampoline};
+#[cfg(not(feature = "std"))]
+use core::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "std")]
+use std::cell::Cell;
+
+/// How deep direct calls may nest when the remaining stack cannot be
+/// measured and no maximum depth was given.
+const FALLBACK_MAX_DEPTH: usize = 1000;
+
+/// Runs trampolines in hybrid mode: the calls made with
+/// `rec_call!(direct => ...)` run the callee right away, on the native
+/// stack, as long as enough stack is left, and only return a
+/// `BorrowRec::Call` to the trampoline once the stack runs low. Shallow
+/// recursions then run at native speed, while deep ones still take bounded
+/// native stack. Calls made with plain `rec_call!` are always returned to
+/// the trampoline.
+///
+/// A direct call runs while the locals of its caller are still alive,
+/// whereas the trampoline runs a call once its caller returned and dropped
+/// them. A `RefCell` borrow or a lock guard which is held across a call
+/// made with `rec_call!(direct => ...)` is then still held by the callee,
+/// which may panic or deadlock when taking it again.
+///
+/// With the `std` feature on Linux, the stack left is measured, and direct
+/// calls are made while more than the red zone is left. Elsewhere, direct
+/// calls are counted and at most `max_depth` of them nest, 1000 by default.
+/// Without the `std` feature, the mode and the count cannot be kept per
+/// thread, so direct calls are only made in a mode made `global`, and
+/// otherwise trampolines run as with `tramp`.
+///
+/// ```rust
+/// #[macro_use] extern crate tramp;
+///
+/// use tramp::{Hybrid, Rec};
+///
+/// fn count(n: u64, acc: u64) -> Rec<u64> {
+///     if n > 0 {
+///         rec_call!(direct => count(n - 1, acc + 1))
+///     } else {
+///         rec_ret!(acc)
+///     }
+/// }
+///
+/// let hybrid = Hybrid::new().max_depth(100);
+/// assert_eq!(hybrid.run(count(1000000, 0)), 1000000);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Hybrid {
+    red_zone: usize,
+    max_depth: Option<usize>,
+    // Only read without the `std` feature.
+    #[cfg_attr(feature = "std", allow(dead_code))]
+    global: bool,
+}
+
+impl Default for Hybrid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hybrid {
+    /// Creates a hybrid mode with a red zone of 256 KiB, without maximum
+    /// depth when the stack left can be measured.
+    pub const fn new() -> Self {
+        Self {
+            red_zone: 256 * 1024,
+            max_depth: None,
+            global: false,
+        }
+    }
+
+    /// Makes direct calls only while more than `bytes` of stack are left.
+    pub fn red_zone(self, bytes: usize) -> Self {
+        Self {
+            red_zone: bytes,
+            ..self
+        }
+    }
+
+    /// Lets at most `depth` direct calls nest, even when the stack left can
+    /// be measured.
+    pub fn max_depth(self, depth: usize) -> Self {
+        Self {
+            max_depth: Some(depth),
+            ..self
+        }
+    }
+
+    /// Lets the mode make direct calls without the `std` feature, where it
+    /// is global to all threads: while it is entered, the calls made with
+    /// `rec_call!(direct => ...)` on any thread may be run right away, so
+    /// no other thread may make such calls then. With the `std` feature,
+    /// the mode is per thread, and this does nothing.
+    pub fn global(self) -> Self {
+        Self {
+            global: true,
+            ..self
+        }
+    }
+
+    /// Runs the trampoline in hybrid mode.
+    pub fn run<R: Trampoline>(&self, res: R) -> R::Output {
+        self.enter(|| tramp(res))
+    }
+
+    /// Runs `fun` in hybrid mode, e.g. to drive a trampoline with something
+    /// else than `tramp`. Calls made with `rec_call!(direct => ...)` while
+    /// `fun` runs may be run right away.
+    pub fn enter<F, U>(&self, fun: F) -> U
+    where
+        F: FnOnce() -> U,
+    {
+        let _mode = Mode {
+            prev: replace_mode(Some(*self)),
+        };
+        fun()
+    }
+
+    /// Whether another direct call can be made, with `depth` of them nested.
+    fn has_room(&self, depth: usize) -> bool {
+        match remaining_stack() {
+            Some(left) => {
+                left > self.red_zone
+                    && self.max_depth.is_none_or(|max| depth < max)
+            },
+            None => depth < self.max_depth.unwrap_or(FALLBACK_MAX_DEPTH),
+        }
+    }
+}
+
+/// Runs the trampoline in the default hybrid mode; see `Hybrid`.
+pub fn tramp_hybrid<'a, T>(res: BorrowRec<'a, T>) -> T {
+    Hybrid::new().run(res)
+}
+
+/// Restores the previous mode when dropped.
+struct Mode {
+    prev: Option<Hybrid>,
+}
+
+impl Drop for Mode {
+    fn drop(&mut self) {
+        replace_mode(self.prev);
+    }
+}
+
+/// A direct call being made; leaves it when dropped.
+pub(crate) struct Direct {
+    _private: (),
+}
+
+impl Direct {
+    /// Enters a direct call, if in hybrid mode and there is room for it.
+    pub(crate) fn enter() -> Option<Self> {
+        let mode = current_mode()?;
+        if !enter_depth(|depth| mode.has_room(depth)) {
+            return None;
+        }
+        Some(Self { _private: () })
+    }
+}
+
+impl Drop for Direct {
+    fn drop(&mut self) {
+        leave_depth();
+    }
+}
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    static MODE: Cell<Option<Hybrid>> = const { Cell::new(None) };
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+#[cfg(feature = "std")]
+fn current_mode() -> Option<Hybrid> {
+    MODE.with(Cell::get)
+}
+
+#[cfg(feature = "std")]
+fn replace_mode(mode: Option<Hybrid>) -> Option<Hybrid> {
+    MODE.with(|cell| cell.replace(mode))
+}
+
+/// Counts one more direct call if there is room for it with the current
+/// count.
+#[cfg(feature = "std")]
+fn enter_depth<F>(has_room: F) -> bool
+where
+    F: FnOnce(usize) -> bool,
+{
+    DEPTH.with(|cell| {
+        let depth = cell.get();
+        let entered = has_room(depth);
+        if entered {
+            cell.set(depth + 1);
+        }
+        entered
+    })
+}
+
+#[cfg(feature = "std")]
+fn leave_depth() {
+    DEPTH.with(|cell| cell.set(cell.get() - 1))
+}
+
+/// The maximum depth of the global mode plus one, or zero outside of it.
+/// Only the maximum depth is kept, since the stack cannot be measured.
+#[cfg(not(feature = "std"))]
+static MODE: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(not(feature = "std"))]
+static DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(not(feature = "std"))]
+fn current_mode() -> Option<Hybrid> {
+    decode_mode(MODE.load(Ordering::Relaxed))
+}
+
+#[cfg(not(feature = "std"))]
+fn replace_mode(mode: Option<Hybrid>) -> Option<Hybrid> {
+    let mode = match mode {
+        Some(mode) if mode.global => {
+            let depth = mode.max_depth.unwrap_or(FALLBACK_MAX_DEPTH);
+            depth.saturating_add(1)
+        },
+        _ => 0,
+    };
+    decode_mode(MODE.swap(mode, Ordering::Relaxed))
+}
+
+#[cfg(not(feature = "std"))]
+fn decode_mode(mode: usize) -> Option<Hybrid> {
+    let depth = mode.checked_sub(1)?;
+    Some(Hybrid::new().max_depth(depth).global())
+}
+
+#[cfg(not(feature = "std"))]
+fn enter_depth<F>(has_room: F) -> bool
+where
+    F: Fn(usize) -> bool,
+{
+    DEPTH
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |depth| {
+            if has_room(depth) {
+                Some(depth + 1)
+            } else {
+                None
+            }
+        })
+        .is_ok()
+}
+
+#[cfg(not(feature = "std"))]
+fn leave_depth() {
+    DEPTH.fetch_sub(1, Ordering::Relaxed);
+}
+
+/// How many bytes of native stack are left to the current thread, if it
+/// can be measured.
+#[cfg(all(feature = "std", target_os = "linux"))]
+fn remaining_stack() -> Option<usize> {
+    std::thread_local! {
+        static STACK_START: Cell<Option<Option<usize>>> =
+            const { Cell::new(None) };
+    }
+
+    let start = STACK_START.with(|cell| match cell.get() {
+        Some(start) => start,
+        None => {
+            let start = stack::start();
+            cell.set(Some(start));
+            start
+        },
+    })?;
+    let here = 0u8;
+    let here = core::hint::black_box(&here) as *const u8 as usize;
+    Some(here.saturating_sub(start))
+}
+
+#[cfg(not(all(feature = "std", target_os = "linux")))]
+fn remaining_stack() -> Option<usize> {
+    None
+}
+
+#[cfg(all(feature = "std", target_os = "linux"))]
+mod stack {
+    use std::{
+        mem::MaybeUninit,
+        os::raw::{c_int, c_ulong, c_void},
+        ptr,
+    };
+
+    /// Opaque `pthread_attr_t`, larger than it is on any Linux target.
+    #[repr(C)]
+    struct Attr([u64; 16]);
+
+    extern "C" {
+        fn pthread_self() -> c_ulong;
+        fn pthread_getattr_np(thread: c_ulong, attr: *mut Attr) -> c_int;
+        fn pthread_attr_getstack(
+            attr: *const Attr,
+            addr: *mut *mut c_void,
+            size: *mut usize,
+        ) -> c_int;
+        fn pthread_attr_destroy(attr: *mut Attr) -> c_int;
+    }
+
+    /// The lowest address of the stack of the current thread, which grows
+    /// down towards it.
+    pub(super) fn start() -> Option<usize> {
+        let mut attr = MaybeUninit::<Attr>::uninit();
+        let mut addr = ptr::null_mut();
+        let mut size = 0;
+        // The attributes are only read once initialized by
+        // `pthread_getattr_np`, and destroyed after that.
+        unsafe {
+            if pthread_getattr_np(pthread_self(), attr.as_mut_ptr()) != 0 {
+                return None;
+            }
+            let res =
+                pthread_attr_getstack(attr.as_ptr(), &mut addr, &mut size);
+            pthread_attr_destroy(attr.as_mut_ptr());
+            if res != 0 {
+                return None;
+            }
+        }
+        Some(addr as usize)
+    }
+}
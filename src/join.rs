@@ -0,0 +1,317 @@
+use crate::SendThunk;
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::fmt;
+#[cfg(feature = "std")]
+use core::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "std")]
+use std::{panic, thread};
+
+/// The function combining the results of the sub-computations of a fork.
+type Combine<'a, T> = Box<dyn FnOnce(Vec<T>) -> JoinRec<'a, T> + Send + 'a>;
+
+/// Sub-computations whose results are combined into the result of the
+/// computation; see `JoinRec::join`.
+pub struct Fork<'a, T> {
+    subs: Vec<JoinRec<'a, T>>,
+    combine: Combine<'a, T>,
+}
+
+impl<'a, T> fmt::Debug for Fork<'a, T> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.debug_struct("Fork")
+            .field("subs", &self.subs.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A divide-and-conquer computation: a step may call several
+/// sub-computations and combine their results. It is run by `tramp_join`,
+/// keeping the pending sub-computations on the heap, or with the `std`
+/// feature by `Parallel::run`, which runs sub-computations on several
+/// threads. The calls are made with `JoinRec::defer`, whose closures must be
+/// `Send`, so that the sub-computations can be run on other threads.
+///
+/// ```rust
+/// use tramp::{tramp_join, JoinRec};
+///
+/// fn merge(left: Vec<u32>, right: Vec<u32>) -> Vec<u32> {
+///     let mut merged = Vec::with_capacity(left.len() + right.len());
+///     let mut right = right.into_iter().peekable();
+///     for x in left {
+///         while let Some(y) = right.next_if(|&y| y < x) {
+///             merged.push(y);
+///         }
+///         merged.push(x);
+///     }
+///     merged.extend(right);
+///     merged
+/// }
+///
+/// fn sort(mut items: Vec<u32>) -> JoinRec<'static, Vec<u32>> {
+///     if items.len() < 2 {
+///         return JoinRec::Ret(items);
+///     }
+///     let right = items.split_off(items.len() / 2);
+///     let halves = vec![
+///         JoinRec::defer(move || sort(items)),
+///         JoinRec::defer(move || sort(right)),
+///     ];
+///     JoinRec::join(halves, |mut halves| {
+///         let right = halves.pop().unwrap();
+///         let left = halves.pop().unwrap();
+///         JoinRec::Ret(merge(left, right))
+///     })
+/// }
+///
+/// assert_eq!(tramp_join(sort(vec![5, 3, 8, 1, 9, 2])), [1, 2, 3, 5, 8, 9]);
+/// ```
+#[derive(Debug)]
+pub enum JoinRec<'a, T> {
+    /// This variant is returned when the computation is done; i.e. this
+    /// the result of the computation.
+    Ret(T),
+    /// This variant is returned when the function is about to call itself
+    /// or another in a tail position.
+    Call(SendThunk<'a, JoinRec<'a, T>>),
+    /// This variant is returned when the function needs the results of
+    /// several sub-computations.
+    Join(Fork<'a, T>),
+}
+
+impl<'a, T> JoinRec<'a, T> {
+    /// Delays the given computation, turning it into a `JoinRec::Call`.
+    #[track_caller]
+    pub fn defer<F>(fun: F) -> Self
+    where
+        F: FnOnce() -> Self + Send + 'a,
+    {
+        JoinRec::Call(SendThunk::new(fun))
+    }
+
+    /// Runs the given sub-computations, then passes their results, in the
+    /// same order, to `combine`, whose result is the result of the whole.
+    pub fn join<F>(subs: Vec<Self>, combine: F) -> Self
+    where
+        F: FnOnce(Vec<T>) -> Self + Send + 'a,
+    {
+        JoinRec::Join(Fork {
+            subs,
+            combine: Box::new(combine),
+        })
+    }
+}
+
+/// A fork whose sub-computations are being run, one after the other.
+struct Frame<'a, T> {
+    subs: vec::IntoIter<JoinRec<'a, T>>,
+    results: Vec<T>,
+    combine: Combine<'a, T>,
+}
+
+/// Runs the computation, keeping the pending forks on a heap stack. Forks
+/// are given to `fork` along with how many forks they are nested in; it may
+/// run them itself and return the combined computation, or return them back
+/// to be run on the current thread.
+fn drive<'a, T, F>(mut res: JoinRec<'a, T>, mut fork: F) -> T
+where
+    F: FnMut(Fork<'a, T>, usize) -> Result<JoinRec<'a, T>, Fork<'a, T>>,
+{
+    let mut stack: Vec<Frame<'a, T>> = Vec::new();
+    loop {
+        res = match res {
+            JoinRec::Call(thunk) => thunk.compute(),
+            JoinRec::Join(forked) => match fork(forked, stack.len()) {
+                Ok(res) => res,
+                Err(Fork { subs, combine }) => {
+                    let mut subs = subs.into_iter();
+                    match subs.next() {
+                        Some(first) => {
+                            stack.push(Frame {
+                                results: Vec::with_capacity(subs.len() + 1),
+                                subs,
+                                combine,
+                            });
+                            first
+                        },
+                        None => combine(Vec::new()),
+                    }
+                },
+            },
+            JoinRec::Ret(x) => {
+                let frame = match stack.last_mut() {
+                    Some(frame) => frame,
+                    None => break x,
+                };
+                frame.results.push(x);
+                match frame.subs.next() {
+                    Some(next) => next,
+                    None => {
+                        let frame = stack.pop().expect("frame was just seen");
+                        (frame.combine)(frame.results)
+                    },
+                }
+            },
+        }
+    }
+}
+
+/// Runs the fork/join computation on the current thread. The
+/// sub-computations are run one after the other, and the pending ones are
+/// kept on the heap, so any depth of recursion takes constant native stack.
+pub fn tramp_join<'a, T>(res: JoinRec<'a, T>) -> T {
+    drive(res, |fork, _| Err(fork))
+}
+
+/// Runs fork/join computations on several threads: the sub-computations of
+/// a fork are run on new threads, as long as fewer than the given number of
+/// threads are busy and the fork is not nested too deep, and on the current
+/// thread otherwise. The threads are scoped, so the computations may borrow
+/// their environment. Whatever the cutoff, forks which get no new thread
+/// are kept on the heap as with `tramp_join`, and only a few forks are ever
+/// nested on the native stack of a thread, so any depth of recursion is
+/// fine.
+///
+/// ```rust
+/// use tramp::{JoinRec, Parallel};
+///
+/// fn sum(items: &[u64]) -> JoinRec<'_, u64> {
+///     if items.len() < 1000 {
+///         return JoinRec::Ret(items.iter().sum());
+///     }
+///     let (left, right) = items.split_at(items.len() / 2);
+///     let halves = vec![
+///         JoinRec::defer(move || sum(left)),
+///         JoinRec::defer(move || sum(right)),
+///     ];
+///     JoinRec::join(halves, |halves| JoinRec::Ret(halves.iter().sum()))
+/// }
+///
+/// let items = (0..100000).collect::<Vec<u64>>();
+/// let parallel = Parallel::new().threads(4).cutoff(3);
+/// assert_eq!(parallel.run(sum(&items)), 4999950000);
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct Parallel {
+    threads: usize,
+    cutoff: usize,
+}
+
+#[cfg(feature = "std")]
+impl Default for Parallel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Parallel {
+    /// Runs on as many threads as the machine can run in parallel, and
+    /// forks nested in up to 8 others.
+    pub fn new() -> Self {
+        Self {
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            cutoff: 8,
+        }
+    }
+
+    /// Runs on at most `threads` threads, including the current one.
+    pub fn threads(self, threads: usize) -> Self {
+        Self {
+            threads: threads.max(1),
+            ..self
+        }
+    }
+
+    /// Runs forks nested in `depth` others or more on the thread they were
+    /// reached on.
+    pub fn cutoff(self, depth: usize) -> Self {
+        Self {
+            cutoff: depth,
+            ..self
+        }
+    }
+
+    /// Runs the fork/join computation. If it panics on any thread, the panic
+    /// goes on on the current thread.
+    pub fn run<'a, T>(&self, res: JoinRec<'a, T>) -> T
+    where
+        T: Send,
+    {
+        let idle = AtomicUsize::new(self.threads - 1);
+        self.run_nested(res, 0, 0, &idle)
+    }
+
+    /// Runs a computation whose forks are nested in `depth` others, on a
+    /// thread where `native` forks are already being run on the native
+    /// stack, with `idle` threads left.
+    fn run_nested<'a, T>(
+        &self,
+        res: JoinRec<'a, T>,
+        depth: usize,
+        native: usize,
+        idle: &AtomicUsize,
+    ) -> T
+    where
+        T: Send,
+    {
+        drive(res, |fork, nested| {
+            let depth = depth + nested;
+            if depth >= self.cutoff
+                || native >= MAX_NATIVE_FORKS
+                || fork.subs.len() < 2
+            {
+                return Err(fork);
+            }
+            let spawned = reserve(idle, fork.subs.len() - 1);
+            if spawned == 0 {
+                return Err(fork);
+            }
+            let Fork { subs, combine } = fork;
+            let results = thread::scope(|scope| {
+                // The first sub-computations run here, the last ones on the
+                // reserved threads, all spawned before running anything.
+                let mut subs = subs.into_iter();
+                let here = subs.len() - spawned;
+                let here = subs.by_ref().take(here).collect::<Vec<_>>();
+                let handles = subs
+                    .map(|sub| {
+                        scope.spawn(move || {
+                            let x = self.run_nested(sub, depth + 1, 0, idle);
+                            idle.fetch_add(1, Ordering::Release);
+                            x
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                let mut results = here
+                    .into_iter()
+                    .map(|sub| {
+                        self.run_nested(sub, depth + 1, native + 1, idle)
+                    })
+                    .collect::<Vec<_>>();
+                results.extend(handles.into_iter().map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|payload| panic::resume_unwind(payload))
+                }));
+                results
+            });
+            Ok(combine(results))
+        })
+    }
+}
+
+/// How many forks may be run on the native stack of a thread, one inside
+/// the other, whatever the cutoff; deeper forks are run on the heap.
+#[cfg(feature = "std")]
+const MAX_NATIVE_FORKS: usize = 32;
+
+/// Takes up to `wanted` idle threads, returning how many were taken.
+#[cfg(feature = "std")]
+fn reserve(idle: &AtomicUsize, wanted: usize) -> usize {
+    let prev = idle.fetch_update(Ordering::Acquire, Ordering::Relaxed, |n| {
+        Some(n - n.min(wanted))
+    });
+    let prev = prev.unwrap_or_else(|n| n);
+    prev.min(wanted)
+}
@@ -0,0 +1,175 @@
+use crate::{tramp, BorrowRec, Thunk};
+use alloc::rc::Rc;
+use core::{cell::RefCell, fmt, mem};
+
+/// How the value of a `Lazy` is obtained.
+enum State<'a, T> {
+    /// The value is known.
+    Now(T),
+    /// The value is computed by the thunk the first time it is needed.
+    Later(Thunk<'a, BorrowRec<'a, T>>),
+    /// The value is computed by the function every time it is needed.
+    Always(Rc<dyn Fn() -> BorrowRec<'a, T> + 'a>),
+    /// The thunk of a `Later` value is being computed.
+    Forcing,
+    /// The computation of a `Later` value was dropped before it finished,
+    /// e.g. because it panicked, so the value will never be known.
+    Poisoned,
+}
+
+/// Marks the value poisoned if dropped before it is known, i.e. if its
+/// computation is dropped before it finishes.
+struct Poison<'a, T> {
+    state: Rc<RefCell<State<'a, T>>>,
+}
+
+impl<'a, T> Drop for Poison<'a, T> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.state.try_borrow_mut() {
+            if let State::Forcing = *state {
+                *state = State::Poisoned;
+            }
+        }
+    }
+}
+
+/// A lazy value, which can be shared: clones of a `Lazy` refer to the same
+/// value. The value is computed by a `BorrowRec`, so a value may depend on
+/// other lazy values through `Lazy::eval`, and forcing a long chain of them
+/// takes constant native stack.
+///
+/// If the computation of a value panics, or is suspended (e.g. by
+/// `tramp_with_fuel`) and then dropped, the value is poisoned: forcing it
+/// again panics.
+///
+/// Dropping a long chain of values which were not forced drops them
+/// recursively, which may overflow the stack; forcing the last one first
+/// lets the chain be dropped on the trampoline.
+///
+/// ```rust
+/// use tramp::Lazy;
+///
+/// let mut value = Lazy::now(0u64);
+/// for _ in 0..100000 {
+///     let prev = value.clone();
+///     value = Lazy::later(move || prev.eval().map(|x| x + 1));
+/// }
+/// assert_eq!(value.force(), 100000);
+/// ```
+pub struct Lazy<'a, T> {
+    state: Rc<RefCell<State<'a, T>>>,
+}
+
+impl<'a, T> Lazy<'a, T> {
+    fn with_state(state: State<'a, T>) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(state)),
+        }
+    }
+
+    /// Creates a value which is already known.
+    pub fn now(value: T) -> Self {
+        Self::with_state(State::Now(value))
+    }
+
+    /// Creates a value computed by `fun` the first time it is needed. The
+    /// result is then cached, and shared by all clones. The computation
+    /// must not depend on the value itself, or forcing it panics.
+    #[track_caller]
+    pub fn later<F>(fun: F) -> Self
+    where
+        F: FnOnce() -> BorrowRec<'a, T> + 'a,
+    {
+        Self::with_state(State::Later(Thunk::new(fun)))
+    }
+
+    /// Creates a value computed by `fun` every time it is needed; nothing is
+    /// cached.
+    pub fn always<F>(fun: F) -> Self
+    where
+        F: Fn() -> BorrowRec<'a, T> + 'a,
+    {
+        Self::with_state(State::Always(Rc::new(fun)))
+    }
+
+    /// Whether the value is known, i.e. it was created with `Lazy::now`, or
+    /// created with `Lazy::later` and already forced.
+    pub fn is_known(&self) -> bool {
+        matches!(*self.state.borrow(), State::Now(_))
+    }
+}
+
+impl<'a, T> Lazy<'a, T>
+where
+    T: Clone + 'a,
+{
+    /// Returns the computation of the value, to be run by `tramp` or
+    /// chained in the computation of another value. Nothing is done until
+    /// it is run.
+    pub fn eval(&self) -> BorrowRec<'a, T> {
+        let this = self.clone();
+        BorrowRec::defer(move || this.start())
+    }
+
+    /// Computes the value, if needed, and returns it.
+    pub fn force(&self) -> T {
+        tramp(self.eval())
+    }
+
+    /// Starts computing the value, now that the trampoline got to it.
+    fn start(self) -> BorrowRec<'a, T> {
+        let mut state = self.state.borrow_mut();
+        match &*state {
+            State::Now(value) => return BorrowRec::Ret(value.clone()),
+            State::Always(fun) => {
+                let fun = fun.clone();
+                drop(state);
+                return fun();
+            },
+            State::Forcing => panic!("lazy value depends on itself"),
+            State::Poisoned => {
+                panic!("lazy value poisoned by an unfinished computation")
+            },
+            State::Later(_) => (),
+        }
+        let thunk = match mem::replace(&mut *state, State::Forcing) {
+            State::Later(thunk) => thunk,
+            _ => unreachable!(),
+        };
+        drop(state);
+        let poison = Poison {
+            state: self.state.clone(),
+        };
+        BorrowRec::Call(thunk).map(move |value| {
+            *poison.state.borrow_mut() = State::Now(value.clone());
+            value
+        })
+    }
+}
+
+impl<'a, T> Clone for Lazy<'a, T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<'a, T> fmt::Debug for Lazy<'a, T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        match &*self.state.borrow() {
+            State::Now(value) => fmtr.debug_tuple("Now").field(value).finish(),
+            State::Later(_) => {
+                fmtr.debug_tuple("Later").finish_non_exhaustive()
+            },
+            State::Always(_) => {
+                fmtr.debug_tuple("Always").finish_non_exhaustive()
+            },
+            State::Forcing => fmtr.debug_tuple("Forcing").finish(),
+            State::Poisoned => fmtr.debug_tuple("Poisoned").finish(),
+        }
+    }
+}
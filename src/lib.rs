@@ -84,10 +84,92 @@
 //!
 //! assert_eq!(factorial(5), 120);
 //! ```
+//!
+//! Recursion which is not in tail position can be written as well, by
+//! chaining what is left to do after the call with `map` or `and_then`.
+//! The pending work is kept on the heap instead of the native stack:
+//!
+//! ```rust
+//! use tramp::{tramp, BorrowRec, Rec};
+//!
+//! fn fac(n: u128) -> Rec<u128> {
+//!     if n > 1 {
+//!         BorrowRec::defer(move || fac(n - 1)).map(move |x| n * x)
+//!     } else {
+//!         BorrowRec::Ret(1)
+//!     }
+//! }
+//!
+//! assert_eq!(tramp(fac(5)), 120);
+//! ```
 #![no_std]
 extern crate alloc;
-use alloc::boxed::Box;
-use core::fmt;
+#[cfg(feature = "std")]
+extern crate std;
+
+use core::ops::ControlFlow;
+
+mod arena;
+mod async_rec;
+mod bind;
+mod cancel;
+#[cfg(feature = "std")]
+mod chrome;
+mod drop;
+mod fix;
+mod future;
+mod graph;
+mod hybrid;
+mod join;
+mod lazy;
+mod memo;
+mod observe;
+mod send;
+mod thunk;
+#[cfg(feature = "std")]
+mod trace;
+mod try_rec;
+mod yield_rec;
+
+pub use arena::{tramp_in, tramp_scoped, Arena, Scope};
+pub use async_rec::{atramp, atramp_send, AsyncRec, SendAsyncRec};
+pub use bind::Bind;
+#[cfg(feature = "std")]
+pub use cancel::tramp_until;
+pub use cancel::{tramp_cancellable, CancelToken};
+#[cfg(feature = "std")]
+pub use chrome::ChromeTrace;
+pub use drop::{drop_children, drop_iteratively, DropChildren};
+pub use fix::{fix, fix2};
+pub use future::{tramp_future, TrampFuture};
+pub use graph::CallGraph;
+pub use hybrid::{tramp_hybrid, Hybrid};
+#[cfg(feature = "std")]
+pub use join::Parallel;
+pub use join::{tramp_join, Fork, JoinRec};
+pub use lazy::Lazy;
+pub use memo::{tramp_memo, Memo, MemoRec};
+pub use observe::{tramp_observed, Observer};
+pub use send::{BorrowSendRec, SendRec, SendThunk};
+pub use thunk::{CallSite, Thunk, INLINE_SIZE};
+#[cfg(feature = "std")]
+pub use trace::{
+    tramp_catch_traced,
+    tramp_traced,
+    LogicalBacktrace,
+    TracedPanic,
+};
+pub use try_rec::{try_tramp, BorrowTryRec, TryRec};
+pub use yield_rec::{YieldRec, Yields};
+
+/// Turns a plain tail-recursive function into a trampolined one, keeping
+/// its signature. Requires the `macros` feature.
+#[cfg(feature = "macros")]
+pub use tramp_macros::tramp;
+/// Turns a group of mutually recursive functions into a state machine
+/// which recurses without allocating. Requires the `macros` feature.
+#[cfg(feature = "macros")]
+pub use tramp_macros::tramp_group;
 
 /// A single recursive-function result with static lifetime.
 pub type Rec<T> = BorrowRec<'static, T>;
@@ -102,73 +184,278 @@ pub enum BorrowRec<'a, T> {
     /// or another in a tail position (i.e. there is no job after the call),
     /// generally indicates recursion.
     Call(Thunk<'a, BorrowRec<'a, T>>),
+    /// This variant is returned when the function still has work to do
+    /// after a call (i.e. the call is not in tail position). It is built by
+    /// [`BorrowRec::and_then`] and [`BorrowRec::map`].
+    Bind(Bind<'a, T>),
 }
 
-trait FnThunk {
-    type Out;
+impl<'a, T> BorrowRec<'a, T> {
+    /// Delays the given computation, turning it into a `BorrowRec::Call`.
+    /// This is the expression counterpart of `rec_call!`, useful to build
+    /// calls which are not in tail position.
+    #[track_caller]
+    pub fn defer<F>(fun: F) -> Self
+    where
+        F: FnOnce() -> Self + 'a,
+    {
+        BorrowRec::Call(Thunk::new(fun))
+    }
 
-    fn call_boxed(self: Box<Self>) -> Self::Out;
-}
+    /// Makes a tail call to `fun`, with the given label: in hybrid mode
+    /// (see `Hybrid`), runs it right away if enough stack is left, and
+    /// otherwise delays it like `BorrowRec::defer`. This is what
+    /// `rec_call!(direct => x)` expands to.
+    #[track_caller]
+    pub fn direct_call<F>(fun: F, label: Option<&'static str>) -> Self
+    where
+        F: FnOnce() -> Self + 'a,
+    {
+        if let Some(_direct) = hybrid::Direct::enter() {
+            return fun();
+        }
+        let thunk = Thunk::new(fun);
+        BorrowRec::Call(match label {
+            Some(label) => thunk.with_label(label),
+            None => thunk,
+        })
+    }
 
-/// A delayed computation. This can be used in lazy evaluation environments.
-/// Also, it is used to delay a tail call and emulate TCO (tail call
-/// optimization).
-pub struct Thunk<'a, T> {
-    fun: Box<dyn FnThunk<Out = T> + 'a>,
-}
+    /// Chains a computation after this one: once this one is done, its
+    /// result is passed to `fun`, and the result of the whole is the result
+    /// of the returned `BorrowRec`. The continuation is kept on a heap
+    /// stack driven by `tramp`, so any depth of non-tail recursion runs in
+    /// constant native stack.
+    ///
+    /// ```rust
+    /// use tramp::{tramp, BorrowRec, Rec};
+    ///
+    /// fn fib(n: u64) -> Rec<u64> {
+    ///     if n < 2 {
+    ///         return BorrowRec::Ret(n);
+    ///     }
+    ///     BorrowRec::defer(move || fib(n - 1)).and_then(move |a| {
+    ///         BorrowRec::defer(move || fib(n - 2)).map(move |b| a + b)
+    ///     })
+    /// }
+    ///
+    /// assert_eq!(tramp(fib(20)), 6765);
+    /// ```
+    pub fn and_then<U, F>(self, fun: F) -> BorrowRec<'a, U>
+    where
+        T: 'a,
+        U: 'a,
+        F: FnOnce(T) -> BorrowRec<'a, U> + 'a,
+    {
+        bind::bind(self, fun)
+    }
 
-impl<T, F> FnThunk for F
-where
-    F: FnOnce() -> T,
-{
-    type Out = T;
+    /// Maps the result of this computation once it is done. Like
+    /// [`BorrowRec::and_then`], this takes constant native stack.
+    pub fn map<U, F>(self, fun: F) -> BorrowRec<'a, U>
+    where
+        T: 'a,
+        U: 'a,
+        F: FnOnce(T) -> U + 'a,
+    {
+        self.and_then(move |x| BorrowRec::Ret(fun(x)))
+    }
 
-    fn call_boxed(self: Box<Self>) -> T {
-        (*self)()
+    /// Runs a single step of the trampoline: if the computation is done,
+    /// its result is returned in `ControlFlow::Break`, otherwise one thunk
+    /// is computed and the rest of the computation is returned in
+    /// `ControlFlow::Continue`. This allows driving a computation from
+    /// outside, e.g. from an event loop.
+    pub fn step(self) -> ControlFlow<T, Self> {
+        match self {
+            BorrowRec::Ret(x) => ControlFlow::Break(x),
+            BorrowRec::Call(thunk) => ControlFlow::Continue(thunk.compute()),
+            BorrowRec::Bind(bind) => ControlFlow::Continue(bind.step()),
+        }
     }
-}
 
-impl<'a, T> Thunk<'a, T> {
-    /// Creates a new thunk from the given function. Probably you will end up
-    /// passing closures to this function.
-    pub fn new(fun: impl FnOnce() -> T + 'a) -> Self {
-        Self {
-            fun: Box::new(fun),
+    /// The call the next step of this computation makes, if it makes one,
+    /// i.e. if it computes a thunk rather than returning or running a
+    /// continuation.
+    pub fn call_site(&self) -> Option<CallSite> {
+        match self {
+            BorrowRec::Ret(_) => None,
+            BorrowRec::Call(thunk) => Some(CallSite::of(thunk)),
+            BorrowRec::Bind(bind) => bind.call_site(),
         }
     }
+}
+
+/// A computation which can be run a step at a time, e.g. a `BorrowRec` or a
+/// hand-written state machine. The drivers of this crate which do not look
+/// into the calls of the computation (`tramp`, `tramp_with_fuel`,
+/// `tramp_observed`, `TrampFuture`, `Hybrid::run`, ...) accept any type
+/// implementing it; those recording call sites, such as `tramp_traced`,
+/// need a `BorrowRec`.
+///
+/// ```rust
+/// use std::ops::ControlFlow;
+/// use tramp::{tramp, Trampoline};
+///
+/// struct Collatz {
+///     n: u64,
+///     steps: u32,
+/// }
+///
+/// impl Trampoline for Collatz {
+///     type Output = u32;
+///
+///     fn step(self) -> ControlFlow<u32, Self> {
+///         let Collatz { n, steps } = self;
+///         match n {
+///             1 => ControlFlow::Break(steps),
+///             n if n % 2 == 0 => ControlFlow::Continue(Collatz {
+///                 n: n / 2,
+///                 steps: steps + 1,
+///             }),
+///             n => ControlFlow::Continue(Collatz {
+///                 n: 3 * n + 1,
+///                 steps: steps + 1,
+///             }),
+///         }
+///     }
+/// }
+///
+/// assert_eq!(tramp(Collatz { n: 27, steps: 0 }), 111);
+/// ```
+pub trait Trampoline: Sized {
+    /// The result of the computation.
+    type Output;
 
-    /// Computes the result of this thunk, i.e. forces evaluation to happen.
-    pub fn compute(self) -> T {
-        self.fun.call_boxed()
+    /// Runs a single step of the computation: if it is done, its result is
+    /// returned in `ControlFlow::Break`, otherwise the rest of the
+    /// computation is returned in `ControlFlow::Continue`.
+    fn step(self) -> ControlFlow<Self::Output, Self>;
+
+    /// Takes the result out of the computation if it is already done,
+    /// without running any step; otherwise, the computation is given back.
+    /// Drivers limiting the number of steps use it to avoid suspending a
+    /// computation which is done. By default, the computation is always
+    /// given back.
+    fn try_finish(self) -> Result<Self::Output, Self> {
+        Err(self)
     }
 }
 
-impl<'a, T> fmt::Debug for Thunk<'a, T> {
-    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmtr, "thunk {} ptr: {:?} {}", '{', &*self.fun as *const _, '}')
+impl<'a, T> Trampoline for BorrowRec<'a, T> {
+    type Output = T;
+
+    fn step(self) -> ControlFlow<T, Self> {
+        BorrowRec::step(self)
+    }
+
+    fn try_finish(self) -> Result<T, Self> {
+        match self {
+            BorrowRec::Ret(x) => Ok(x),
+            res => Err(res),
+        }
     }
 }
 
-/// Given an input which of type `BorrowRec`, this function performs
-/// a trampoline over the value. While `Rec::Call(thunk)` is returned,
-/// this function will keep evauating `thunk`. Whenever `Rec::Done(x)` is
-/// found, `x` is returned.
-pub fn tramp<'a, T>(mut res: BorrowRec<'a, T>) -> T {
+/// Given an input of a type implementing `Trampoline`, e.g. `BorrowRec`,
+/// this function performs a trampoline over the value. While
+/// `Rec::Call(thunk)` is returned, this function will keep evauating
+/// `thunk`. Whenever `Rec::Done(x)` is found, `x` is returned.
+pub fn tramp<R: Trampoline>(mut res: R) -> R::Output {
     loop {
-        match res {
-            BorrowRec::Ret(x) => break x,
-            BorrowRec::Call(thunk) => res = thunk.compute(),
+        match res.step() {
+            ControlFlow::Break(x) => break x,
+            ControlFlow::Continue(next) => res = next,
         }
     }
 }
 
+/// Like `tramp`, but runs at most `steps` steps (i.e. computes at most
+/// `steps` thunks of a `BorrowRec`). If the computation is
+/// not done by then, it is suspended and returned in `Err`, so it can be
+/// resumed later by calling this function (or `tramp`) on it again.
+///
+/// ```rust
+/// #[macro_use] extern crate tramp;
+///
+/// use tramp::{tramp_with_fuel, Rec};
+///
+/// fn count(n: u64, acc: u64) -> Rec<u64> {
+///     if n > 0 {
+///         rec_call!(count(n - 1, acc + 1))
+///     } else {
+///         rec_ret!(acc)
+///     }
+/// }
+///
+/// let mut res = count(1000, 0);
+/// let mut slices = 1;
+/// let total = loop {
+///     match tramp_with_fuel(res, 100) {
+///         Ok(total) => break total,
+///         Err(suspended) => {
+///             res = suspended;
+///             slices += 1;
+///         },
+///     }
+/// };
+/// assert_eq!(total, 1000);
+/// assert_eq!(slices, 10);
+/// ```
+pub fn tramp_with_fuel<R: Trampoline>(
+    mut res: R,
+    steps: usize,
+) -> Result<R::Output, R> {
+    for _ in 0..steps {
+        match res.step() {
+            ControlFlow::Break(x) => return Ok(x),
+            ControlFlow::Continue(next) => res = next,
+        }
+    }
+    res.try_finish()
+}
+
 /// Turns a (probably recursive) tail call into a return value of
 /// type `Rec`. The variant created is `Rec::Call`.
 /// Given an expression `x` of type `T`, then `rec_call!(x)` has type `Rec<T>`.
 /// It is equivalent to, given a fictional attribute "optimize_tail_call",
 /// `#[optimize_tail_call] return x` transforming `T` into `Rec<T>`.
+///
+/// `rec_call!(in scope => x)` does the same, but creates the thunk through
+/// the given [`Scope`], storing it in the scope's arena.
+///
+/// When `x` is a plain call such as `f(a, b)`, the thunk is labeled with
+/// the name of the called function (see `Thunk::with_label`).
+///
+/// `rec_call!(direct => x)` does the same, but in hybrid mode (see
+/// `Hybrid`), `x` is evaluated right away while enough stack is left,
+/// instead of being returned to the trampoline. It is then evaluated while
+/// the locals of the calling function are still alive, whereas the
+/// trampoline only evaluates it once they are dropped: a `RefCell` borrow
+/// or a lock guard held across such a call is still held by the callee.
 #[macro_export]
 macro_rules! rec_call {
+    (in $scope:expr => $call:expr) => {
+        return $crate::Scope::call($scope, move || $call);
+    };
+    (direct => $fun:ident $(:: $path:ident)* ($($arg:expr),* $(,)?)) => {
+        return $crate::BorrowRec::direct_call(
+            move || $fun $(:: $path)*($($arg),*),
+            Some(concat!(stringify!($fun) $(, "::", stringify!($path))*)),
+        );
+    };
+    (direct => $call:expr) => {
+        return $crate::BorrowRec::direct_call(move || $call, None);
+    };
+    ($fun:ident $(:: $path:ident)* ($($arg:expr),* $(,)?)) => {
+        return $crate::BorrowRec::Call(
+            $crate::Thunk::new(move || $fun $(:: $path)*($($arg),*))
+                .with_label(concat!(
+                    stringify!($fun)
+                    $(, "::", stringify!($path))*
+                )),
+        );
+    };
     ($call:expr) => {
         return $crate::BorrowRec::Call($crate::Thunk::new(move || $call));
     };
@@ -183,3 +470,110 @@ macro_rules! rec_ret {
         return $crate::BorrowRec::Ret($val);
     };
 }
+
+/// Turns a (probably recursive) tail call into a return value of type
+/// `SendRec`, which can be sent to another thread. The variant created is
+/// `SendRec::Call`. Given an expression `x` of type `T`, then
+/// `send_rec_call!(x)` has type `SendRec<T>`. Unlike `rec_call!`, the
+/// captures of the call must be `Send`. Plain calls are labeled as with
+/// `rec_call!`.
+#[macro_export]
+macro_rules! send_rec_call {
+    ($fun:ident $(:: $path:ident)* ($($arg:expr),* $(,)?)) => {
+        return $crate::BorrowSendRec::Call(
+            $crate::SendThunk::new(move || $fun $(:: $path)*($($arg),*))
+                .with_label(concat!(
+                    stringify!($fun)
+                    $(, "::", stringify!($path))*
+                )),
+        );
+    };
+    ($call:expr) => {
+        return $crate::BorrowSendRec::Call($crate::SendThunk::new(move || $call));
+    };
+}
+
+/// Returns a value from a `SendRec`-function. This means the recursion is
+/// done. Given an expression `x` of type `T`, then `send_rec_ret!(x)` has
+/// type `SendRec<T>`.
+#[macro_export]
+macro_rules! send_rec_ret {
+    ($val:expr) => {
+        return $crate::BorrowSendRec::Ret($val);
+    };
+}
+
+/// Turns a (probably recursive) async tail call into a return value of type
+/// `AsyncRec`. The variant created is `AsyncRec::Call`. Given an expression
+/// `x` of type `impl Future<Output = AsyncRec<T>>`, e.g. the call of an
+/// `async fn`, then `async_rec_call!(x)` has type `AsyncRec<T>`.
+#[macro_export]
+macro_rules! async_rec_call {
+    ($call:expr) => {
+        return $crate::AsyncRec::call($call);
+    };
+}
+
+/// Returns a value from an `AsyncRec`-function. This means the recursion is
+/// done. Given an expression `x` of type `T`, then `async_rec_ret!(x)` has
+/// type `AsyncRec<T>`.
+#[macro_export]
+macro_rules! async_rec_ret {
+    ($val:expr) => {
+        return $crate::AsyncRec::Ret($val);
+    };
+}
+
+/// Turns a (probably recursive) async tail call into a return value of type
+/// `SendAsyncRec`. The variant created is `SendAsyncRec::Call`. Given an
+/// expression `x` of type `impl Future<Output = SendAsyncRec<T>> + Send`,
+/// then `send_async_rec_call!(x)` has type `SendAsyncRec<T>`.
+#[macro_export]
+macro_rules! send_async_rec_call {
+    ($call:expr) => {
+        return $crate::SendAsyncRec::call($call);
+    };
+}
+
+/// Returns a value from a `SendAsyncRec`-function. This means the recursion
+/// is done. Given an expression `x` of type `T`, then
+/// `send_async_rec_ret!(x)` has type `SendAsyncRec<T>`.
+#[macro_export]
+macro_rules! send_async_rec_ret {
+    ($val:expr) => {
+        return $crate::SendAsyncRec::Ret($val);
+    };
+}
+
+/// Yields an item from a `YieldRec`-function, and then performs the given
+/// tail call, if any. Given an expression `y` of type `Y` and an expression
+/// `x` of type `YieldRec<Y>`, then `rec_yield!(y => x)` has type
+/// `YieldRec<Y>`, and so does `rec_yield!(y)`, which ends the function after
+/// yielding.
+#[macro_export]
+macro_rules! rec_yield {
+    ($item:expr => $call:expr) => {
+        return $crate::YieldRec::Yield($item, $crate::Thunk::new(move || $call));
+    };
+    ($item:expr) => {
+        return $crate::YieldRec::Yield(
+            $item,
+            $crate::Thunk::new(|| $crate::YieldRec::Ret),
+        );
+    };
+}
+
+/// Unwraps a `Result` in a fallible recursive function (one returning a
+/// `TryRec`). If the result is an error, it is returned from the function,
+/// converted with `From`, which ends the whole trampoline with it.
+/// Given an expression `x` of type `Result<T, E>`, then `rec_try!(x)` has
+/// type `T`. It is equivalent to `x?` for `TryRec`-functions.
+#[macro_export]
+macro_rules! rec_try {
+    ($res:expr) => {
+        match $res {
+            Ok(x) => x,
+            Err(e) => return $crate::BorrowRec::Ret(Err(From::from(e))),
+        }
+    };
+}
@@ -0,0 +1,271 @@
+use crate::{tramp, BorrowRec};
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    rc::Rc,
+    vec::Vec,
+};
+use core::{
+    cell::{Cell, RefCell},
+    fmt,
+    mem,
+};
+
+/// The cache of a run of `Memo::run`, shared by its `MemoRec`s.
+struct State<K, V> {
+    cache: RefCell<BTreeMap<K, V>>,
+    /// The keys whose values are being computed.
+    pending: RefCell<BTreeSet<K>>,
+    hits: Cell<u64>,
+    misses: Cell<u64>,
+}
+
+/// The body of a memoized function.
+type Body<'a, K, V> = dyn Fn(K, &MemoRec<'a, K, V>) -> BorrowRec<'a, V> + 'a;
+
+/// Handle given to the body of a memoized function to request the values
+/// of other keys. Requests are not made by native recursion but return a
+/// `BorrowRec`, to be chained with `map` and `and_then`. A handle can be
+/// cloned to be moved into a continuation.
+pub struct MemoRec<'a, K, V> {
+    state: Rc<State<K, V>>,
+    body: Rc<Body<'a, K, V>>,
+}
+
+impl<'a, K, V> MemoRec<'a, K, V>
+where
+    K: Ord + Clone + 'a,
+    V: Clone + 'a,
+{
+    /// The value of the given key: the cached value, if any, or else the
+    /// computation of the value, which caches it once done.
+    ///
+    /// # Panics
+    /// Running the computation panics if the value depends on itself.
+    pub fn call(&self, key: K) -> BorrowRec<'a, V> {
+        if let Some(value) = self.cached(&key) {
+            return BorrowRec::Ret(value);
+        }
+        let this = self.clone();
+        BorrowRec::defer(move || this.start(key))
+    }
+
+    /// The values of the given keys, in order. They are computed one after
+    /// the other, so that a value computed for a key is reused by the next
+    /// ones.
+    pub fn call_all<I>(&self, keys: I) -> BorrowRec<'a, Vec<V>>
+    where
+        I: IntoIterator<Item = K>,
+    {
+        let mut res = BorrowRec::Ret(Vec::new());
+        for key in keys {
+            let this = self.clone();
+            res = res.and_then(move |mut values| {
+                this.call(key).map(move |value| {
+                    values.push(value);
+                    values
+                })
+            });
+        }
+        res
+    }
+
+    fn cached(&self, key: &K) -> Option<V> {
+        let value = self.state.cache.borrow().get(key).cloned();
+        if value.is_some() {
+            self.state.hits.set(self.state.hits.get() + 1);
+        }
+        value
+    }
+
+    /// Starts computing the value of `key`, now that the trampoline got to
+    /// it; it may have been computed in the meantime.
+    fn start(self, key: K) -> BorrowRec<'a, V> {
+        if let Some(value) = self.cached(&key) {
+            return BorrowRec::Ret(value);
+        }
+        if !self.state.pending.borrow_mut().insert(key.clone()) {
+            panic!("memoized value depends on itself");
+        }
+        self.state.misses.set(self.state.misses.get() + 1);
+        let state = self.state.clone();
+        (self.body)(key.clone(), &self).map(move |value| {
+            state.pending.borrow_mut().remove(&key);
+            state.cache.borrow_mut().insert(key, value.clone());
+            value
+        })
+    }
+}
+
+impl<'a, K, V> Clone for MemoRec<'a, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            body: self.body.clone(),
+        }
+    }
+}
+
+impl<'a, K, V> fmt::Debug for MemoRec<'a, K, V> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.debug_struct("MemoRec")
+            .field("cached", &self.state.cache.borrow().len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A cache of the values of a memoized recursive function, which may be
+/// kept across runs, along with statistics on its use.
+///
+/// ```rust
+/// use tramp::{Memo, MemoRec, Rec};
+///
+/// fn fib(n: u64, rec: &MemoRec<'static, u64, u64>) -> Rec<u64> {
+///     if n < 2 {
+///         Rec::Ret(n)
+///     } else {
+///         rec.call_all([n - 1, n - 2]).map(|v| v[0].wrapping_add(v[1]))
+///     }
+/// }
+///
+/// let mut memo = Memo::new();
+/// assert_eq!(memo.run(90, fib), 2880067194370816120);
+/// assert_eq!(memo.misses(), 91);
+///
+/// // The values computed by the previous run are reused.
+/// assert_eq!(memo.run(80, fib), 23416728348467685);
+/// assert_eq!(memo.misses(), 91);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Memo<K, V> {
+    cache: BTreeMap<K, V>,
+    hits: u64,
+    misses: u64,
+}
+
+impl<K, V> Default for Memo<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> Memo<K, V> {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        Self {
+            cache: BTreeMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// How many values are cached.
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Whether no value is cached.
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// How many requested values were found in the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// How many values were computed.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Empties the cache, keeping the statistics.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+}
+
+impl<K, V> Memo<K, V>
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    /// The cached value of the given key, if any.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.cache.get(key)
+    }
+
+    /// Computes the value of `key` with the memoized function `body`, which
+    /// requests the values of other keys through the given `MemoRec`. The
+    /// values are computed on the trampoline, so the recursion may be as
+    /// deep as needed, and they are all kept in the cache. If `body` panics,
+    /// the values computed before are still kept.
+    ///
+    /// # Panics
+    /// Panics if a value depends on itself.
+    pub fn run<'a, F>(&mut self, key: K, body: F) -> V
+    where
+        K: 'a,
+        V: 'a,
+        F: Fn(K, &MemoRec<'a, K, V>) -> BorrowRec<'a, V> + 'a,
+    {
+        let state = Rc::new(State {
+            cache: RefCell::new(mem::take(&mut self.cache)),
+            pending: RefCell::new(BTreeSet::new()),
+            hits: Cell::new(self.hits),
+            misses: Cell::new(self.misses),
+        });
+        let _restore = Restore {
+            memo: self,
+            state: state.clone(),
+        };
+        let rec = MemoRec {
+            state,
+            body: Rc::new(body),
+        };
+        tramp(rec.call(key))
+    }
+}
+
+/// Puts the cache and the statistics of a run back into the `Memo` when
+/// dropped, also if the run panics: the cache only holds values which were
+/// completely computed.
+struct Restore<'m, K, V> {
+    memo: &'m mut Memo<K, V>,
+    state: Rc<State<K, V>>,
+}
+
+impl<'m, K, V> Drop for Restore<'m, K, V> {
+    fn drop(&mut self) {
+        if let Ok(mut cache) = self.state.cache.try_borrow_mut() {
+            self.memo.cache = mem::take(&mut *cache);
+        }
+        self.memo.hits = self.state.hits.get();
+        self.memo.misses = self.state.misses.get();
+    }
+}
+
+/// Computes the value of `key` with the memoized recursive function `body`,
+/// with a fresh cache; see `Memo::run`.
+///
+/// ```rust
+/// use tramp::{tramp_memo, BorrowRec};
+///
+/// // The number of paths from the top left corner of a grid to its bottom
+/// // right corner, going only right and down.
+/// let paths = tramp_memo((16u64, 16u64), |(x, y), rec| {
+///     if x == 0 || y == 0 {
+///         BorrowRec::Ret(1u64)
+///     } else {
+///         rec.call_all([(x - 1, y), (x, y - 1)]).map(|v| v[0] + v[1])
+///     }
+/// });
+/// assert_eq!(paths, 601080390);
+/// ```
+pub fn tramp_memo<'a, K, V, F>(key: K, body: F) -> V
+where
+    K: Ord + Clone + 'a,
+    V: Clone + 'a,
+    F: Fn(K, &MemoRec<'a, K, V>) -> BorrowRec<'a, V> + 'a,
+{
+    Memo::new().run(key, body)
+}
@@ -0,0 +1,92 @@
+use crate::Trampoline;
+use core::ops::ControlFlow;
+
+/// Watches a trampoline run by `tramp_observed`, e.g. to count its steps,
+/// enforce a quota or log its progress.
+pub trait Observer<R: Trampoline> {
+    /// Called before every step, starting with the first, with the
+    /// computation about to be stepped, which is not done yet. Returning
+    /// `ControlFlow::Break` stops the trampoline, which then returns the
+    /// computation, suspended.
+    fn on_step(&mut self, res: &R) -> ControlFlow<()>;
+
+    /// Called with the result, once the computation is done. Does nothing by
+    /// default.
+    fn on_ret(&mut self, _result: &R::Output) {}
+}
+
+impl<R, O> Observer<R> for &mut O
+where
+    R: Trampoline,
+    O: Observer<R> + ?Sized,
+{
+    fn on_step(&mut self, res: &R) -> ControlFlow<()> {
+        (**self).on_step(res)
+    }
+
+    fn on_ret(&mut self, result: &R::Output) {
+        (**self).on_ret(result)
+    }
+}
+
+/// Like `tramp`, but notifies the given observer of every step and of the
+/// result. If the observer asks to stop, the computation is suspended and
+/// returned in `Err`, so the caller may either drop it or resume it later.
+///
+/// ```rust
+/// #[macro_use] extern crate tramp;
+///
+/// use std::ops::ControlFlow;
+/// use tramp::{tramp_observed, Observer, Rec};
+///
+/// fn count(n: u64, acc: u64) -> Rec<u64> {
+///     if n > 0 {
+///         rec_call!(count(n - 1, acc + 1))
+///     } else {
+///         rec_ret!(acc)
+///     }
+/// }
+///
+/// struct Quota(u64);
+///
+/// impl<R: tramp::Trampoline> Observer<R> for Quota {
+///     fn on_step(&mut self, _: &R) -> ControlFlow<()> {
+///         if self.0 == 0 {
+///             return ControlFlow::Break(());
+///         }
+///         self.0 -= 1;
+///         ControlFlow::Continue(())
+///     }
+/// }
+///
+/// assert_eq!(tramp_observed(count(10, 0), &mut Quota(100)).ok(), Some(10));
+/// assert!(tramp_observed(count(1000, 0), &mut Quota(100)).is_err());
+/// ```
+pub fn tramp_observed<R, O>(
+    mut res: R,
+    observer: &mut O,
+) -> Result<R::Output, R>
+where
+    R: Trampoline,
+    O: Observer<R> + ?Sized,
+{
+    loop {
+        res = match res.try_finish() {
+            Ok(x) => {
+                observer.on_ret(&x);
+                break Ok(x);
+            },
+            Err(res) => res,
+        };
+        if observer.on_step(&res).is_break() {
+            break Err(res);
+        }
+        match res.step() {
+            ControlFlow::Break(x) => {
+                observer.on_ret(&x);
+                break Ok(x);
+            },
+            ControlFlow::Continue(next) => res = next,
+        }
+    }
+}
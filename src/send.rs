@@ -0,0 +1,152 @@
+use crate::{BorrowRec, Thunk, Trampoline};
+use core::{fmt, ops::ControlFlow, panic::Location};
+
+/// A delayed computation which can be sent to another thread: it can only
+/// be created from a `Send` closure. Like `Thunk`, small closures are stored
+/// inline.
+pub struct SendThunk<'a, T> {
+    thunk: Thunk<'a, T>,
+}
+
+// The thunk only holds a closure which is `Send`. The result is produced by
+// computing the thunk, on the thread it was sent to.
+unsafe impl<'a, T> Send for SendThunk<'a, T> {}
+
+// No method of a shared `SendThunk` touches the closure.
+unsafe impl<'a, T> Sync for SendThunk<'a, T> {}
+
+impl<'a, T> SendThunk<'a, T> {
+    /// Creates a new thunk from the given `Send` function.
+    #[track_caller]
+    pub fn new<F>(fun: F) -> Self
+    where
+        F: FnOnce() -> T + Send + 'a,
+    {
+        Self {
+            thunk: Thunk::new(fun),
+        }
+    }
+
+    /// Computes the result of this thunk, i.e. forces evaluation to happen.
+    pub fn compute(self) -> T {
+        self.thunk.compute()
+    }
+
+    /// Labels this thunk. See `Thunk::with_label`.
+    pub fn with_label(self, label: &'static str) -> Self {
+        Self {
+            thunk: self.thunk.with_label(label),
+        }
+    }
+
+    /// The label of this thunk. See `Thunk::label`.
+    pub fn label(&self) -> Option<&'static str> {
+        self.thunk.label()
+    }
+
+    /// Where this thunk was created. See `Thunk::location`.
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.thunk.location()
+    }
+
+    /// Forgets that this thunk can be sent to another thread.
+    pub fn into_thunk(self) -> Thunk<'a, T> {
+        self.thunk
+    }
+}
+
+impl<'a, T> fmt::Debug for SendThunk<'a, T> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        self.thunk.fmt_as("SendThunk", fmtr)
+    }
+}
+
+/// A single recursive-function result with static lifetime, which can be
+/// sent to another thread.
+pub type SendRec<T> = BorrowSendRec<'static, T>;
+
+/// A single borrowed recursive-function result, which can be sent to
+/// another thread: a computation suspended e.g. by `tramp_with_fuel` can
+/// be resumed by another worker thread. The calls are made with
+/// `send_rec_call!`, which requires their captures to be `Send`.
+///
+/// ```rust
+/// #[macro_use] extern crate tramp;
+///
+/// use std::thread;
+/// use tramp::{tramp, tramp_with_fuel, SendRec};
+///
+/// fn count(n: u64, acc: u64) -> SendRec<u64> {
+///     if n > 0 {
+///         send_rec_call!(count(n - 1, acc + 1))
+///     } else {
+///         send_rec_ret!(acc)
+///     }
+/// }
+///
+/// let suspended = tramp_with_fuel(count(1000, 0), 100).unwrap_err();
+/// let worker = thread::spawn(move || tramp(suspended));
+/// assert_eq!(worker.join().unwrap(), 1000);
+/// ```
+#[derive(Debug)]
+pub enum BorrowSendRec<'a, T> {
+    /// This variant is returned when the function is done; i.e. this the
+    /// result of the computation.
+    Ret(T),
+    /// This variant is returned when the function is about to call itself
+    /// or another in a tail position.
+    Call(SendThunk<'a, BorrowSendRec<'a, T>>),
+}
+
+impl<'a, T> BorrowSendRec<'a, T> {
+    /// Delays the given computation, turning it into a
+    /// `BorrowSendRec::Call`.
+    #[track_caller]
+    pub fn defer<F>(fun: F) -> Self
+    where
+        F: FnOnce() -> Self + Send + 'a,
+    {
+        BorrowSendRec::Call(SendThunk::new(fun))
+    }
+
+    /// Runs a single step of the trampoline. See `BorrowRec::step`.
+    pub fn step(self) -> ControlFlow<T, Self> {
+        match self {
+            BorrowSendRec::Ret(x) => ControlFlow::Break(x),
+            BorrowSendRec::Call(thunk) => {
+                ControlFlow::Continue(thunk.compute())
+            },
+        }
+    }
+}
+
+impl<'a, T> Trampoline for BorrowSendRec<'a, T> {
+    type Output = T;
+
+    fn step(self) -> ControlFlow<T, Self> {
+        BorrowSendRec::step(self)
+    }
+
+    fn try_finish(self) -> Result<T, Self> {
+        match self {
+            BorrowSendRec::Ret(x) => Ok(x),
+            res => Err(res),
+        }
+    }
+}
+
+/// Forgets that the computation can be sent to another thread, e.g. to
+/// chain more computations after it with `BorrowRec::and_then`.
+impl<'a, T> From<BorrowSendRec<'a, T>> for BorrowRec<'a, T>
+where
+    T: 'a,
+{
+    fn from(res: BorrowSendRec<'a, T>) -> Self {
+        match res {
+            BorrowSendRec::Ret(x) => BorrowRec::Ret(x),
+            BorrowSendRec::Call(thunk) => {
+                BorrowRec::Call(thunk.into_thunk().map(Into::into))
+            },
+        }
+    }
+}
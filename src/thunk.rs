@@ -0,0 +1,395 @@
+use crate::arena::Arena;
+use alloc::{
+    alloc::{alloc, dealloc, handle_alloc_error, Layout},
+    boxed::Box,
+};
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem::{self, ManuallyDrop, MaybeUninit},
+    panic::Location,
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+/// Number of bytes a closure may capture and still be stored inline in a
+/// `Thunk`, without touching the heap.
+pub const INLINE_SIZE: usize = 4 * mem::size_of::<usize>();
+
+/// Inline storage of a thunk. Aligned to 16 bytes so that closures
+/// capturing `u128`s fit in it.
+#[repr(C, align(16))]
+#[derive(Clone, Copy)]
+struct Storage {
+    bytes: [MaybeUninit<u8>; INLINE_SIZE],
+}
+
+impl Storage {
+    fn uninit() -> Self {
+        Self {
+            bytes: [MaybeUninit::uninit(); INLINE_SIZE],
+        }
+    }
+
+    /// Whether a value of type `F` fits in the inline buffer.
+    fn fits<F>() -> bool {
+        mem::size_of::<F>() <= INLINE_SIZE
+            && mem::align_of::<F>() <= mem::align_of::<Storage>()
+    }
+
+    fn as_mut_ptr<F>(&mut self) -> *mut F {
+        self.bytes.as_mut_ptr() as *mut F
+    }
+}
+
+/// Calls a closure stored inline, consuming it.
+unsafe fn call_inline<F, T>(storage: &mut Storage) -> T
+where
+    F: FnOnce() -> T,
+{
+    ptr::read(storage.as_mut_ptr::<F>())()
+}
+
+/// Drops a closure stored inline without calling it.
+unsafe fn drop_inline<F>(storage: &mut Storage) {
+    ptr::drop_in_place(storage.as_mut_ptr::<F>())
+}
+
+/// Header of a heap block holding a closure too big to be stored inline.
+/// The closure follows the header, aligned as it requires.
+struct Block {
+    layout: Layout,
+}
+
+/// A heap block kept around after its closure was taken out of it, so that
+/// the next big thunk can reuse it instead of going through the allocator.
+/// In a trampoline, a thunk is computed right before the next one is
+/// created, so a single spare block makes steady-state recursion
+/// allocation-free.
+static SPARE: AtomicPtr<Block> = AtomicPtr::new(ptr::null_mut());
+
+impl Block {
+    /// Layout of a block holding an `F` and the offset of the `F` in it.
+    fn layout_for<F>() -> (Layout, usize) {
+        let (layout, offset) = Layout::new::<Block>()
+            .extend(Layout::new::<F>())
+            .expect("thunk closure too big");
+        (layout.pad_to_align(), offset)
+    }
+
+    /// Finds a block able to hold an `F`, reusing the spare block if it is
+    /// big enough.
+    unsafe fn acquire<F>() -> *mut Block {
+        let (layout, offset) = Self::layout_for::<F>();
+        let spare = SPARE.swap(ptr::null_mut(), Ordering::Acquire);
+        if !spare.is_null() {
+            let spare_layout = (*spare).layout;
+            if spare_layout.align() >= layout.align()
+                && spare_layout.size() >= offset + mem::size_of::<F>()
+            {
+                return spare;
+            }
+            dealloc(spare as *mut u8, spare_layout);
+        }
+        let block = alloc(layout) as *mut Block;
+        if block.is_null() {
+            handle_alloc_error(layout);
+        }
+        ptr::write(block, Block { layout });
+        block
+    }
+
+    /// Gives a block back, keeping it as the spare block if there is none.
+    unsafe fn release(block: *mut Block) {
+        let kept = SPARE.compare_exchange(
+            ptr::null_mut(),
+            block,
+            Ordering::Release,
+            Ordering::Relaxed,
+        );
+        if kept.is_err() {
+            dealloc(block as *mut u8, (*block).layout);
+        }
+    }
+
+    /// Pointer to the `F` stored in this block.
+    unsafe fn payload<F>(block: *mut Block) -> *mut F {
+        let (_, offset) = Self::layout_for::<F>();
+        (block as *mut u8).add(offset) as *mut F
+    }
+}
+
+/// Calls a closure stored in a heap block whose pointer is in the inline
+/// buffer.
+unsafe fn call_boxed<F, T>(storage: &mut Storage) -> T
+where
+    F: FnOnce() -> T,
+{
+    let block = ptr::read(storage.as_mut_ptr::<*mut Block>());
+    let fun = ptr::read(Block::payload::<F>(block));
+    Block::release(block);
+    fun()
+}
+
+/// Drops a closure stored in a heap block without calling it.
+unsafe fn drop_boxed<F>(storage: &mut Storage) {
+    let block = ptr::read(storage.as_mut_ptr::<*mut Block>());
+    let fun = ptr::read(Block::payload::<F>(block));
+    Block::release(block);
+    drop(fun)
+}
+
+/// Calls a closure stored in an arena, whose pointer is in the inline buffer.
+unsafe fn call_arena<F, T>(storage: &mut Storage) -> T
+where
+    F: FnOnce() -> T,
+{
+    let fun: *mut F = ptr::read(storage.as_mut_ptr());
+    ptr::read(fun)()
+}
+
+/// Drops a closure stored in an arena without calling it. The memory is
+/// only given back when the arena is reset or dropped.
+unsafe fn drop_arena<F>(storage: &mut Storage) {
+    ptr::drop_in_place(ptr::read(storage.as_mut_ptr::<*mut F>()))
+}
+
+/// Where a thunk was created, and what it calls, if known.
+#[cfg(feature = "debug-info")]
+#[derive(Clone, Copy)]
+struct Info {
+    location: &'static Location<'static>,
+    label: Option<&'static str>,
+}
+
+#[cfg(feature = "debug-info")]
+impl Info {
+    #[track_caller]
+    fn here() -> Self {
+        Self {
+            location: Location::caller(),
+            label: None,
+        }
+    }
+
+    fn set_label(&mut self, label: &'static str) {
+        self.label = Some(label);
+    }
+
+    fn label(&self) -> Option<&'static str> {
+        self.label
+    }
+
+    fn location(&self) -> Option<&'static Location<'static>> {
+        Some(self.location)
+    }
+}
+
+/// Without the `debug-info` feature, nothing is recorded.
+#[cfg(not(feature = "debug-info"))]
+#[derive(Clone, Copy)]
+struct Info;
+
+#[cfg(not(feature = "debug-info"))]
+impl Info {
+    fn here() -> Self {
+        Info
+    }
+
+    fn set_label(&mut self, _label: &'static str) {}
+
+    fn label(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn location(&self) -> Option<&'static Location<'static>> {
+        None
+    }
+}
+
+/// A delayed computation. This can be used in lazy evaluation environments.
+/// Also, it is used to delay a tail call and emulate TCO (tail call
+/// optimization).
+///
+/// Closures capturing up to [`INLINE_SIZE`] bytes are stored inline, so
+/// creating and computing them does not allocate. Bigger closures are
+/// stored on the heap, in a block that is recycled by the next big thunk
+/// once this one is computed or dropped.
+///
+/// With the `debug-info` feature, a thunk records where it was created and
+/// an optional label, e.g. the name of the function it calls, and prints
+/// them in its `Debug` output. `rec_call!` labels its thunks with the name
+/// of the called function, when it is given as a plain call.
+pub struct Thunk<'a, T> {
+    storage: Storage,
+    call: unsafe fn(&mut Storage) -> T,
+    drop: unsafe fn(&mut Storage),
+    info: Info,
+    _marker: PhantomData<Box<dyn FnOnce() -> T + 'a>>,
+}
+
+impl<'a, T> Thunk<'a, T> {
+    /// Creates a thunk from its parts, recording where the caller was called
+    /// from.
+    #[track_caller]
+    fn from_parts(
+        storage: Storage,
+        call: unsafe fn(&mut Storage) -> T,
+        drop: unsafe fn(&mut Storage),
+    ) -> Self {
+        Self {
+            storage,
+            call,
+            drop,
+            info: Info::here(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a new thunk from the given function. Probably you will end up
+    /// passing closures to this function.
+    #[track_caller]
+    pub fn new<F>(fun: F) -> Self
+    where
+        F: FnOnce() -> T + 'a,
+    {
+        let mut storage = Storage::uninit();
+        if Storage::fits::<F>() {
+            unsafe { ptr::write(storage.as_mut_ptr::<F>(), fun) };
+            Self::from_parts(storage, call_inline::<F, T>, drop_inline::<F>)
+        } else {
+            unsafe {
+                let block = Block::acquire::<F>();
+                ptr::write(Block::payload::<F>(block), fun);
+                ptr::write(storage.as_mut_ptr::<*mut Block>(), block);
+            }
+            Self::from_parts(storage, call_boxed::<F, T>, drop_boxed::<F>)
+        }
+    }
+
+    /// Creates a new thunk whose closure, if too big to be stored inline,
+    /// is stored in the given arena instead of the heap.
+    #[track_caller]
+    pub(crate) fn new_in<F>(arena: &'a Arena, fun: F) -> Self
+    where
+        F: FnOnce() -> T + 'a,
+    {
+        if Storage::fits::<F>() {
+            return Self::new(fun);
+        }
+        let mut storage = Storage::uninit();
+        unsafe {
+            let place = arena.alloc(Layout::new::<F>()) as *mut F;
+            ptr::write(place, fun);
+            ptr::write(storage.as_mut_ptr::<*mut F>(), place);
+        }
+        Self::from_parts(storage, call_arena::<F, T>, drop_arena::<F>)
+    }
+
+    /// Labels this thunk, e.g. with the name of the function it calls. The
+    /// label is only kept with the `debug-info` feature.
+    pub fn with_label(mut self, label: &'static str) -> Self {
+        self.info.set_label(label);
+        self
+    }
+
+    /// The label of this thunk, if any. Always `None` without the
+    /// `debug-info` feature.
+    pub fn label(&self) -> Option<&'static str> {
+        self.info.label()
+    }
+
+    /// Where this thunk was created. Always `None` without the `debug-info`
+    /// feature.
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.info.location()
+    }
+
+    /// Wraps this thunk in one passing its result to `fun`, which keeps the
+    /// location and label of this one.
+    pub(crate) fn map<U, F>(self, fun: F) -> Thunk<'a, U>
+    where
+        T: 'a,
+        F: FnOnce(T) -> U + 'a,
+    {
+        let info = self.info;
+        let mut thunk = Thunk::new(move || fun(self.compute()));
+        thunk.info = info;
+        thunk
+    }
+
+    /// Writes the location and label of this thunk as a struct named
+    /// `name`.
+    pub(crate) fn fmt_as(
+        &self,
+        name: &str,
+        fmtr: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        let mut debug = fmtr.debug_struct(name);
+        if let Some(label) = self.label() {
+            debug.field("label", &label);
+        }
+        match self.location() {
+            Some(location) => debug
+                .field("location", &format_args!("{}", location))
+                .finish(),
+            None => debug.finish_non_exhaustive(),
+        }
+    }
+
+    /// Computes the result of this thunk, i.e. forces evaluation to happen.
+    pub fn compute(self) -> T {
+        let mut this = ManuallyDrop::new(self);
+        unsafe { (this.call)(&mut this.storage) }
+    }
+}
+
+impl<'a, T> Drop for Thunk<'a, T> {
+    fn drop(&mut self) {
+        unsafe { (self.drop)(&mut self.storage) }
+    }
+}
+
+impl<'a, T> fmt::Debug for Thunk<'a, T> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_as("Thunk", fmtr)
+    }
+}
+
+/// A call made by a trampolined recursion, i.e. the label and location of
+/// its thunk, which are only known with the `debug-info` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallSite {
+    label: Option<&'static str>,
+    location: Option<&'static Location<'static>>,
+}
+
+impl CallSite {
+    pub(crate) fn of<T>(thunk: &Thunk<T>) -> Self {
+        Self {
+            label: thunk.label(),
+            location: thunk.location(),
+        }
+    }
+
+    /// The label of the thunk of the call, usually the name of the called
+    /// function.
+    pub fn label(&self) -> Option<&'static str> {
+        self.label
+    }
+
+    /// Where the thunk of the call was created.
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.location
+    }
+}
+
+impl fmt::Display for CallSite {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.write_str(self.label.unwrap_or("<unknown>"))?;
+        if let Some(location) = self.location {
+            write!(fmtr, " at {}", location)?;
+        }
+        Ok(())
+    }
+}
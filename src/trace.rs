@@ -0,0 +1,141 @@
+use crate::{BorrowRec, CallSite};
+use core::{fmt, ops::ControlFlow};
+use std::{
+    any::Any,
+    boxed::Box,
+    collections::VecDeque,
+    eprintln,
+    panic::{self, AssertUnwindSafe},
+    string::String,
+};
+
+/// The most recent calls of a trampolined recursion, oldest first. Printed
+/// by `tramp_traced` when the recursion panics.
+#[derive(Debug, Clone)]
+pub struct LogicalBacktrace {
+    calls: VecDeque<CallSite>,
+    omitted: usize,
+}
+
+impl LogicalBacktrace {
+    fn new(depth: usize) -> Self {
+        Self {
+            calls: VecDeque::with_capacity(depth),
+            omitted: 0,
+        }
+    }
+
+    fn push(&mut self, call: CallSite, depth: usize) {
+        if depth == 0 {
+            self.omitted += 1;
+            return;
+        }
+        if self.calls.len() == depth {
+            self.calls.pop_front();
+            self.omitted += 1;
+        }
+        self.calls.push_back(call);
+    }
+
+    /// The recorded calls, oldest first.
+    pub fn calls(&self) -> impl Iterator<Item = &CallSite> {
+        self.calls.iter()
+    }
+
+    /// How many calls were made before the recorded ones.
+    pub fn omitted(&self) -> usize {
+        self.omitted
+    }
+}
+
+impl fmt::Display for LogicalBacktrace {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmtr, "logical backtrace (most recent call last):")?;
+        if self.omitted > 0 {
+            write!(fmtr, "\n  ... {} earlier calls", self.omitted)?;
+        }
+        for call in &self.calls {
+            write!(fmtr, "\n  {}", call)?;
+        }
+        Ok(())
+    }
+}
+
+/// A panic caught by `tramp_catch_traced`, along with the logical backtrace
+/// of the recursion when it happened.
+pub struct TracedPanic {
+    /// The payload of the panic, which can be given to
+    /// `std::panic::resume_unwind`.
+    pub payload: Box<dyn Any + Send>,
+    /// The calls which led to the panic.
+    pub backtrace: LogicalBacktrace,
+}
+
+impl TracedPanic {
+    /// The message of the panic, if it is a string.
+    pub fn message(&self) -> Option<&str> {
+        match self.payload.downcast_ref::<&'static str>() {
+            Some(message) => Some(message),
+            None => self.payload.downcast_ref::<String>().map(|s| &**s),
+        }
+    }
+}
+
+impl fmt::Debug for TracedPanic {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.debug_struct("TracedPanic")
+            .field("message", &self.message())
+            .field("backtrace", &self.backtrace)
+            .finish()
+    }
+}
+
+/// Like `tramp`, but keeps the call sites of the last `depth` calls of the
+/// recursion. If the recursion panics, the panic is caught and returned in
+/// `Err` along with these calls.
+pub fn tramp_catch_traced<'a, T>(
+    mut res: BorrowRec<'a, T>,
+    depth: usize,
+) -> Result<T, TracedPanic> {
+    let mut backtrace = LogicalBacktrace::new(depth);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| loop {
+        if let Some(call) = res.call_site() {
+            backtrace.push(call, depth);
+        }
+        match res.step() {
+            ControlFlow::Break(x) => break x,
+            ControlFlow::Continue(next) => res = next,
+        }
+    }));
+    result.map_err(|payload| TracedPanic { payload, backtrace })
+}
+
+/// Like `tramp`, but keeps the call sites of the last `depth` calls of the
+/// recursion, and prints them to the standard error if the recursion
+/// panics, before letting the panic go on. The calls are labeled with the
+/// `debug-info` feature.
+///
+/// ```rust,should_panic
+/// #[macro_use] extern crate tramp;
+///
+/// use tramp::{tramp_traced, Rec};
+///
+/// fn count_down(n: u64) -> Rec<u64> {
+///     if n == 0 {
+///         panic!("reached zero");
+///     }
+///     rec_call!(count_down(n - 1))
+/// }
+///
+/// // Prints the last 10 calls of `count_down` before panicking.
+/// tramp_traced(count_down(1000), 10);
+/// ```
+pub fn tramp_traced<'a, T>(res: BorrowRec<'a, T>, depth: usize) -> T {
+    match tramp_catch_traced(res, depth) {
+        Ok(x) => x,
+        Err(traced) => {
+            eprintln!("{}", traced.backtrace);
+            panic::resume_unwind(traced.payload)
+        },
+    }
+}
@@ -0,0 +1,88 @@
+use crate::{tramp, BorrowRec, Trampoline};
+
+/// A single fallible recursive-function result with static lifetime.
+pub type TryRec<T, E> = BorrowTryRec<'static, T, E>;
+
+/// A single borrowed fallible recursive-function result. This is just a
+/// `BorrowRec` of a `Result`, so `rec_call!` and `rec_ret!` work as usual,
+/// and `rec_try!` returns early with the error.
+pub type BorrowTryRec<'a, T, E> = BorrowRec<'a, Result<T, E>>;
+
+/// Performs a trampoline over a fallible computation, e.g. a `TryRec`. As
+/// soon as some function of the recursion fails, the error is returned.
+///
+/// ```rust
+/// #[macro_use] extern crate tramp;
+///
+/// use tramp::{try_tramp, TryRec};
+///
+/// fn sum_digits(input: &'static [u8], acc: u32) -> TryRec<u32, u8> {
+///     match input.split_first() {
+///         Some((&digit @ b'0'..=b'9', rest)) => {
+///             rec_call!(sum_digits(rest, acc + u32::from(digit - b'0')))
+///         },
+///         Some((&other, _)) => rec_ret!(Err(other)),
+///         None => rec_ret!(Ok(acc)),
+///     }
+/// }
+///
+/// assert_eq!(try_tramp(sum_digits(b"1234", 0)), Ok(10));
+/// assert_eq!(try_tramp(sum_digits(b"12x4", 0)), Err(b'x'));
+/// ```
+pub fn try_tramp<R, T, E>(res: R) -> Result<T, E>
+where
+    R: Trampoline<Output = Result<T, E>>,
+{
+    tramp(res)
+}
+
+impl<'a, T> BorrowRec<'a, T> {
+    /// Turns an infallible computation into a fallible one that always
+    /// succeeds, so infallible helpers can be called from fallible ones.
+    pub fn into_ok<E>(self) -> BorrowTryRec<'a, T, E>
+    where
+        T: 'a,
+        E: 'a,
+    {
+        self.map(Ok)
+    }
+}
+
+impl<'a, T, E> BorrowRec<'a, Result<T, E>> {
+    /// Chains a fallible computation after this one, if it succeeds. If it
+    /// fails, `fun` is skipped and the error is the result of the whole.
+    pub fn and_then_ok<U, F>(self, fun: F) -> BorrowTryRec<'a, U, E>
+    where
+        T: 'a,
+        U: 'a,
+        E: 'a,
+        F: FnOnce(T) -> BorrowTryRec<'a, U, E> + 'a,
+    {
+        self.and_then(move |res| match res {
+            Ok(x) => fun(x),
+            Err(e) => BorrowRec::Ret(Err(e)),
+        })
+    }
+
+    /// Maps the result of this computation, if it succeeds.
+    pub fn map_ok<U, F>(self, fun: F) -> BorrowTryRec<'a, U, E>
+    where
+        T: 'a,
+        U: 'a,
+        E: 'a,
+        F: FnOnce(T) -> U + 'a,
+    {
+        self.map(move |res| res.map(fun))
+    }
+
+    /// Maps the error of this computation, if it fails.
+    pub fn map_err<E2, F>(self, fun: F) -> BorrowTryRec<'a, T, E2>
+    where
+        T: 'a,
+        E: 'a,
+        E2: 'a,
+        F: FnOnce(E) -> E2 + 'a,
+    {
+        self.map(move |res| res.map_err(fun))
+    }
+}
@@ -0,0 +1,141 @@
+use crate::Thunk;
+use alloc::{boxed::Box, vec::Vec};
+use core::{fmt, iter::FusedIterator, mem};
+
+/// A single generator-like recursive-function result: besides returning and
+/// calling, the function can yield items along the way. Turn it into an
+/// iterator with `into_iter` to get the items.
+///
+/// ```rust
+/// #[macro_use] extern crate tramp;
+///
+/// use tramp::YieldRec;
+///
+/// fn countdown(n: u32) -> YieldRec<'static, u32> {
+///     if n > 0 {
+///         rec_yield!(n => countdown(n - 1))
+///     } else {
+///         YieldRec::Ret
+///     }
+/// }
+///
+/// let items: Vec<u32> = countdown(3).into_iter().collect();
+/// assert_eq!(items, [3, 2, 1]);
+/// ```
+pub enum YieldRec<'a, Y> {
+    /// This variant is returned when the function is done.
+    Ret,
+    /// This variant is returned when the function is about to call itself
+    /// or another in a tail position.
+    Call(Thunk<'a, YieldRec<'a, Y>>),
+    /// This variant is returned when the function yields an item and then
+    /// calls itself or another in a tail position.
+    Yield(Y, Thunk<'a, YieldRec<'a, Y>>),
+    /// This variant is returned when the function runs a whole computation
+    /// (yielding its items) and then the thunk. It is built by
+    /// [`YieldRec::then`].
+    Then(Box<YieldRec<'a, Y>>, Thunk<'a, YieldRec<'a, Y>>),
+}
+
+impl<'a, Y> YieldRec<'a, Y> {
+    /// Delays the given computation, turning it into a `YieldRec::Call`.
+    #[track_caller]
+    pub fn defer<F>(fun: F) -> Self
+    where
+        F: FnOnce() -> Self + 'a,
+    {
+        YieldRec::Call(Thunk::new(fun))
+    }
+
+    /// Chains a computation after this one: first the items of this one
+    /// are yielded, then the ones of the computation `fun` returns. The
+    /// computations waiting for their turn are kept on the heap by the
+    /// iterator, so e.g. a tree traversal takes constant native stack.
+    #[track_caller]
+    pub fn then<F>(self, fun: F) -> Self
+    where
+        F: FnOnce() -> Self + 'a,
+    {
+        match self {
+            YieldRec::Ret => YieldRec::defer(fun),
+            this => YieldRec::Then(Box::new(this), Thunk::new(fun)),
+        }
+    }
+}
+
+impl<'a, Y> fmt::Debug for YieldRec<'a, Y>
+where
+    Y: fmt::Debug,
+{
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            YieldRec::Ret => fmtr.write_str("Ret"),
+            YieldRec::Call(thunk) => {
+                fmtr.debug_tuple("Call").field(thunk).finish()
+            }
+            YieldRec::Yield(item, thunk) => {
+                fmtr.debug_tuple("Yield").field(item).field(thunk).finish()
+            }
+            YieldRec::Then(first, thunk) => {
+                fmtr.debug_tuple("Then").field(first).field(thunk).finish()
+            }
+        }
+    }
+}
+
+impl<'a, Y> IntoIterator for YieldRec<'a, Y> {
+    type Item = Y;
+    type IntoIter = Yields<'a, Y>;
+
+    fn into_iter(self) -> Yields<'a, Y> {
+        Yields {
+            current: self,
+            pending: Vec::new(),
+        }
+    }
+}
+
+/// A lazy iterator over the items yielded by a `YieldRec`. Every call to
+/// `next` performs the trampoline only until the next item is yielded.
+pub struct Yields<'a, Y> {
+    current: YieldRec<'a, Y>,
+    pending: Vec<Thunk<'a, YieldRec<'a, Y>>>,
+}
+
+impl<'a, Y> Iterator for Yields<'a, Y> {
+    type Item = Y;
+
+    fn next(&mut self) -> Option<Y> {
+        loop {
+            match mem::replace(&mut self.current, YieldRec::Ret) {
+                YieldRec::Ret => match self.pending.pop() {
+                    Some(thunk) => self.current = thunk.compute(),
+                    None => break None,
+                },
+                YieldRec::Call(thunk) => self.current = thunk.compute(),
+                YieldRec::Yield(item, thunk) => {
+                    self.current = YieldRec::Call(thunk);
+                    break Some(item);
+                }
+                YieldRec::Then(first, thunk) => {
+                    self.pending.push(thunk);
+                    self.current = *first;
+                }
+            }
+        }
+    }
+}
+
+impl<'a, Y> FusedIterator for Yields<'a, Y> {}
+
+impl<'a, Y> fmt::Debug for Yields<'a, Y>
+where
+    Y: fmt::Debug,
+{
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.debug_struct("Yields")
+            .field("current", &self.current)
+            .field("pending", &self.pending.len())
+            .finish()
+    }
+}
@@ -0,0 +1,91 @@
+#[macro_use]
+extern crate tramp;
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+use tramp::{tramp, Rec};
+use tramp_macros::tramp_group;
+
+struct CountingAlloc;
+
+thread_local! {
+    // Per thread, so allocations of the test harness are not counted.
+    static ALLOCS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn allocs() -> usize {
+    ALLOCS.with(Cell::get)
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCS.try_with(|allocs| allocs.set(allocs.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn fac_with_acc(n: u128, acc: u128) -> Rec<u128> {
+    if n > 1 {
+        rec_call!(fac_with_acc(n - 1, acc.wrapping_mul(n)))
+    } else {
+        rec_ret!(acc)
+    }
+}
+
+// Captures too much to be stored inline in a thunk.
+fn fac_with_history(n: u128, acc: u128, history: [u128; 8]) -> Rec<u128> {
+    if n > 1 {
+        let mut history = history;
+        history[(n % 8) as usize] = acc;
+        rec_call!(fac_with_history(n - 1, acc.wrapping_mul(n), history))
+    } else {
+        rec_ret!(acc ^ history.iter().fold(0, |x, y| x ^ y))
+    }
+}
+
+tramp_group! {
+    fn is_even(x: u128) -> bool {
+        if x > 0 {
+            rec_call!(is_odd(x - 1))
+        } else {
+            rec_ret!(true)
+        }
+    }
+
+    fn is_odd(x: u128) -> bool {
+        if x > 0 {
+            rec_call!(is_even(x - 1))
+        } else {
+            rec_ret!(false)
+        }
+    }
+}
+
+#[test]
+fn test_recursion_allocations() {
+    let before = allocs();
+    tramp(fac_with_acc(1_000_000, 1));
+    let small = allocs() - before;
+    assert_eq!(small, 0);
+
+    let before = allocs();
+    tramp(fac_with_history(1_000_000, 1, [0; 8]));
+    let large = allocs() - before;
+    assert!(large <= 2, "{} allocations", large);
+}
+
+#[test]
+fn test_group_allocations() {
+    let before = allocs();
+    assert!(is_even(1_000_000));
+    assert_eq!(allocs() - before, 0);
+}
@@ -0,0 +1,38 @@
+#[macro_use]
+extern crate tramp;
+
+use tramp::{tramp_in, tramp_scoped, Arena, BorrowRec, Scope};
+
+fn fac_with_history<'a>(
+    scope: Scope<'a>,
+    n: u128,
+    acc: u128,
+    history: [u128; 4],
+) -> BorrowRec<'a, u128> {
+    if n > 1 {
+        let mut history = history;
+        history[(n % 4) as usize] = acc;
+        rec_call!(in scope => fac_with_history(scope, n - 1, acc * n, history))
+    } else {
+        rec_ret!(acc)
+    }
+}
+
+#[test]
+fn test_thunks_in_arena() {
+    let mut arena = Arena::new();
+    let fac = tramp_in(&arena, |scope| fac_with_history(scope, 20, 1, [0; 4]));
+    assert_eq!(fac, 2432902008176640000);
+    assert!(arena.allocated() > 0);
+
+    arena.reset();
+    assert_eq!(arena.allocated(), 0);
+    let fac = tramp_in(&arena, |scope| fac_with_history(scope, 5, 1, [0; 4]));
+    assert_eq!(fac, 120);
+}
+
+#[test]
+fn test_scoped() {
+    let fac = tramp_scoped(|scope| fac_with_history(scope, 30, 1, [0; 4]));
+    assert_eq!(fac, 265252859812191058636308480000000);
+}
@@ -0,0 +1,107 @@
+#[macro_use]
+extern crate tramp;
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread,
+};
+use tramp::{atramp, atramp_send, AsyncRec, SendAsyncRec};
+
+struct NoopWaker;
+
+impl Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
+}
+
+fn block_on<F>(future: F) -> F::Output
+where
+    F: Future,
+{
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(x) = future.as_mut().poll(&mut cx) {
+            break x;
+        }
+    }
+}
+
+/// Returns `Pending` once before completing, like a real I/O future.
+struct YieldOnce(bool);
+
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+async fn is_even_rec(x: u128) -> AsyncRec<'static, bool> {
+    if x.is_multiple_of(1000) {
+        YieldOnce(false).await;
+    }
+    if x > 0 {
+        async_rec_call!(is_odd_rec(x - 1))
+    } else {
+        async_rec_ret!(true)
+    }
+}
+
+async fn is_odd_rec(x: u128) -> AsyncRec<'static, bool> {
+    if x > 0 {
+        async_rec_call!(is_even_rec(x - 1))
+    } else {
+        async_rec_ret!(false)
+    }
+}
+
+#[test]
+fn test_async_oddness() {
+    assert!(block_on(atramp(AsyncRec::call(is_even_rec(1_000_000)))));
+    assert!(block_on(atramp(AsyncRec::call(is_odd_rec(1_000_001)))));
+    assert!(!block_on(atramp(AsyncRec::call(is_odd_rec(10)))));
+}
+
+// Not an `async fn`, which could not be proved `Send` while recursive.
+#[allow(clippy::manual_async_fn)]
+fn count(
+    n: u64,
+    acc: u64,
+) -> impl Future<Output = SendAsyncRec<'static, u64>> + Send {
+    async move {
+        if n.is_multiple_of(1000) {
+            YieldOnce(false).await;
+        }
+        if n > 0 {
+            send_async_rec_call!(count(n - 1, acc + 1))
+        } else {
+            send_async_rec_ret!(acc)
+        }
+    }
+}
+
+fn assert_send<T: Send>(x: T) -> T {
+    x
+}
+
+#[test]
+fn test_send_future() {
+    let res = SendAsyncRec::call(count(100000, 0));
+    let future = assert_send(atramp_send(res));
+    let worker = thread::spawn(move || block_on(future));
+    assert_eq!(worker.join().unwrap(), 100000);
+
+    let res: AsyncRec<u64> = SendAsyncRec::call(count(1000, 0)).into();
+    assert_eq!(block_on(atramp(res)), 1000);
+}
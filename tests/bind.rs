@@ -0,0 +1,45 @@
+extern crate tramp;
+
+use tramp::{tramp, BorrowRec, Rec};
+
+fn fac(n: u128) -> Rec<u128> {
+    if n > 1 {
+        BorrowRec::defer(move || fac(n - 1)).map(move |x| x.wrapping_mul(n))
+    } else {
+        BorrowRec::Ret(1)
+    }
+}
+
+fn fib(n: u64) -> Rec<u64> {
+    if n < 2 {
+        return BorrowRec::Ret(n);
+    }
+    BorrowRec::defer(move || fib(n - 1)).and_then(move |a| {
+        BorrowRec::defer(move || fib(n - 2)).map(move |b| a + b)
+    })
+}
+
+fn depth(n: u64) -> Rec<u64> {
+    if n == 0 {
+        return BorrowRec::Ret(0);
+    }
+    BorrowRec::defer(move || depth(n - 1)).and_then(|d| {
+        BorrowRec::defer(move || BorrowRec::Ret(d + 1))
+    })
+}
+
+#[test]
+fn test_non_tail_recursion() {
+    assert_eq!(tramp(fac(20)), 2432902008176640000);
+    assert_eq!(tramp(fib(20)), 6765);
+    assert_eq!(tramp(depth(1_000_000)), 1_000_000);
+}
+
+#[test]
+fn test_left_nested_binds() {
+    let mut rec = BorrowRec::defer(|| BorrowRec::Ret(0u64));
+    for _ in 0..1_000_000 {
+        rec = rec.and_then(|x| BorrowRec::defer(move || BorrowRec::Ret(x + 1)));
+    }
+    assert_eq!(tramp(rec), 1_000_000);
+}
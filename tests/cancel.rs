@@ -0,0 +1,52 @@
+#[macro_use]
+extern crate tramp;
+
+use std::{sync::Arc, thread};
+use tramp::{tramp, tramp_cancellable, CancelToken, Rec};
+
+fn count(n: u64, limit: u64) -> Rec<u64> {
+    if n < limit {
+        rec_call!(count(n + 1, limit))
+    } else {
+        rec_ret!(n)
+    }
+}
+
+#[test]
+fn test_cancel_from_other_thread() {
+    let token = Arc::new(CancelToken::new());
+    let canceller = token.clone();
+    let handle = thread::spawn(move || canceller.cancel());
+    handle.join().unwrap();
+
+    let suspended = tramp_cancellable(count(0, u64::MAX), &token, 64);
+    assert!(suspended.is_err());
+
+    token.reset();
+    let res = tramp_cancellable(count(0, 100_000), &token, 64);
+    assert_eq!(res.ok(), Some(100_000));
+}
+
+#[test]
+fn test_resume_after_cancel() {
+    let token = CancelToken::new();
+    token.cancel();
+    let suspended = tramp_cancellable(count(0, 1000), &token, 10).unwrap_err();
+    assert_eq!(tramp(suspended), 1000);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_deadline() {
+    use std::time::{Duration, Instant};
+    use tramp::tramp_until;
+
+    let deadline = Instant::now() + Duration::from_millis(20);
+    let suspended = tramp_until(count(0, u64::MAX), deadline, 1000);
+    assert!(suspended.is_err());
+    assert!(Instant::now() >= deadline);
+
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let res = tramp_until(count(0, 1000), deadline, 1000);
+    assert_eq!(res.ok(), Some(1000));
+}
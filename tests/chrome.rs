@@ -0,0 +1,76 @@
+#![cfg(feature = "std")]
+
+#[macro_use]
+extern crate tramp;
+
+use tramp::{tramp_observed, BorrowRec, ChromeTrace, Rec};
+
+fn count(n: u64, acc: u64) -> Rec<u64> {
+    if n > 0 {
+        rec_call!(count(n - 1, acc + 1))
+    } else {
+        rec_ret!(acc)
+    }
+}
+
+/// Runs `fun`, which makes a call with `rec_call!`, so that the call and
+/// the line it is made on can be taken in the same statement.
+fn call<T>(fun: impl FnOnce() -> Rec<T>) -> Rec<T> {
+    fun()
+}
+
+fn to_json(trace: &ChromeTrace) -> String {
+    let mut json = Vec::new();
+    trace.write_json(&mut json).unwrap();
+    String::from_utf8(json).unwrap()
+}
+
+#[test]
+fn test_record_all() {
+    let mut trace = ChromeTrace::new();
+    let (res, line) = (call(|| rec_call!(count(99, 1))), line!());
+    assert_eq!(trace.run(res), 100);
+    assert_eq!(trace.len(), 100);
+    assert_eq!(trace.dropped(), 0);
+
+    let json = to_json(&trace);
+    assert!(json.starts_with("{\"traceEvents\":[\n{\"name\":"));
+    assert!(json.ends_with("\"otherData\":{\"steps\":100,\"dropped\":0}}"));
+    assert_eq!(json.matches("\"ph\":\"X\"").count(), 100);
+    assert!(json.contains("\"args\":{\"step\":99"));
+    if cfg!(feature = "debug-info") {
+        assert!(json.contains("{\"name\":\"count\""), "{}", json);
+        let location = format!("\"location\":\"tests/chrome.rs:{}:", line);
+        assert!(json.contains(&location), "{}", json);
+    } else {
+        assert!(json.contains("{\"name\":\"call\""), "{}", json);
+    }
+}
+
+#[test]
+fn test_sample_and_limit() {
+    let mut trace = ChromeTrace::new().sample_every(1000).limit(100);
+    assert_eq!(trace.run(count(1_000_000, 0)), 1_000_000);
+    assert_eq!(trace.len(), 100);
+    assert_eq!(trace.dropped(), 900);
+    let json = to_json(&trace);
+    assert!(json.contains("\"args\":{\"step\":99000"));
+    assert!(!json.contains("\"args\":{\"step\":100000"));
+
+    let mut trace = ChromeTrace::new();
+    let res = count(10, 0).map(|x| x + 1);
+    assert_eq!(trace.run(res), 11);
+    assert!(to_json(&trace).contains("{\"name\":\"continuation\""));
+
+    let mut trace = ChromeTrace::new();
+    assert_eq!(trace.run(BorrowRec::Ret(5)), 5);
+    assert!(trace.is_empty());
+}
+
+#[test]
+fn test_observer() {
+    let mut trace = ChromeTrace::new();
+    assert_eq!(tramp_observed(count(100, 0), &mut trace).ok(), Some(100));
+    assert_eq!(trace.len(), 100);
+    assert!(to_json(&trace).contains("\"otherData\":{\"steps\":100,"));
+}
@@ -0,0 +1,99 @@
+#[macro_use]
+extern crate tramp;
+
+use tramp::{BorrowRec, Rec, SendRec, Thunk};
+
+mod oddness {
+    use tramp::Rec;
+
+    pub fn is_odd(x: u128) -> Rec<bool> {
+        if x > 0 {
+            rec_call!(super::is_even(x - 1))
+        } else {
+            rec_ret!(false)
+        }
+    }
+}
+
+fn is_even(x: u128) -> Rec<bool> {
+    if x > 0 {
+        rec_call!(oddness::is_odd(x - 1))
+    } else {
+        rec_ret!(true)
+    }
+}
+
+fn count(n: u64) -> SendRec<u64> {
+    if n > 0 {
+        send_rec_call!(count(n - 1))
+    } else {
+        send_rec_ret!(0)
+    }
+}
+
+/// Runs `fun`, which makes a call with `rec_call!`, so that the call and
+/// the line it is made on can be taken in the same statement.
+fn call<T>(fun: impl FnOnce() -> Rec<T>) -> Rec<T> {
+    fun()
+}
+
+fn thunk_of<'r, 'a, T>(
+    res: &'r BorrowRec<'a, T>,
+) -> &'r Thunk<'a, BorrowRec<'a, T>> {
+    match res {
+        BorrowRec::Call(thunk) => thunk,
+        _ => panic!("not a call"),
+    }
+}
+
+#[test]
+fn test_labels() {
+    let res = is_even(3);
+    let thunk = thunk_of(&res);
+    let res_odd = oddness::is_odd(3);
+    let thunk_odd = thunk_of(&res_odd);
+
+    if cfg!(feature = "debug-info") {
+        assert_eq!(thunk.label(), Some("oddness::is_odd"));
+        assert_eq!(thunk_odd.label(), Some("super::is_even"));
+
+        let (res, line) = (call(|| rec_call!(oddness::is_odd(3))), line!());
+        let location = thunk_of(&res).location().unwrap();
+        assert_eq!(location.file(), file!());
+        assert_eq!(location.line(), line);
+
+        let debug = format!("{:?}", res);
+        assert!(debug.contains("label: \"oddness::is_odd\""), "{}", debug);
+        let at = format!("tests/debug_info.rs:{}:", line);
+        assert!(debug.contains(&at), "{}", debug);
+    } else {
+        assert_eq!(thunk.label(), None);
+        assert_eq!(thunk.location(), None);
+        assert_eq!(format!("{:?}", res), "Call(Thunk { .. })");
+    }
+}
+
+#[test]
+fn test_unlabeled_and_send() {
+    let (thunk, line) = (Thunk::new(|| 5), line!());
+    let send = count(5);
+    match &send {
+        tramp::BorrowSendRec::Call(thunk) if cfg!(feature = "debug-info") => {
+            assert_eq!(thunk.label(), Some("count"));
+        },
+        tramp::BorrowSendRec::Call(thunk) => assert_eq!(thunk.label(), None),
+        _ => panic!("not a call"),
+    }
+    if cfg!(feature = "debug-info") {
+        assert_eq!(thunk.label(), None);
+        assert_eq!(thunk.location().unwrap().line(), line);
+        let labeled = thunk.with_label("five");
+        assert_eq!(labeled.label(), Some("five"));
+        // Wrapping a computation keeps the labels of its thunks.
+        let res: BorrowRec<u64> = send.into();
+        let debug = format!("{:?}", res);
+        assert!(debug.contains("label: \"count\""), "{}", debug);
+    } else {
+        assert_eq!(thunk.with_label("five").label(), None);
+    }
+}
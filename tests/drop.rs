@@ -0,0 +1,111 @@
+#[macro_use]
+extern crate tramp;
+
+use std::{cell::Cell, mem, rc::Rc};
+use tramp::{drop_iteratively, DropChildren};
+
+struct Node {
+    next: Option<Box<Node>>,
+}
+
+impl DropChildren for Node {
+    fn take_children(&mut self, out: &mut Vec<Self>) {
+        out.extend(self.next.take().map(|next| *next));
+    }
+}
+
+impl_iterative_drop!(Node);
+
+struct List<T> {
+    value: T,
+    next: Option<Box<List<T>>>,
+}
+
+impl<T> DropChildren for List<T> {
+    fn take_children(&mut self, out: &mut Vec<Self>) {
+        out.extend(self.next.take().map(|next| *next));
+    }
+}
+
+impl_iterative_drop!(<T> List<T>);
+
+/// Counts its drops.
+struct Counted(Rc<Cell<u64>>);
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+enum Tree {
+    Leaf(Counted),
+    Branch(Vec<Tree>),
+}
+
+impl DropChildren for Tree {
+    fn take_children(&mut self, out: &mut Vec<Self>) {
+        if let Tree::Branch(children) = self {
+            out.append(children);
+        }
+    }
+}
+
+#[test]
+fn test_long_list() {
+    let mut list = Node { next: None };
+    for _ in 0..10000000 {
+        list = Node {
+            next: Some(Box::new(list)),
+        };
+    }
+    drop(list);
+}
+
+#[test]
+fn test_generic_list() {
+    let drops = Rc::new(Cell::new(0));
+    let mut list = List {
+        value: Counted(drops.clone()),
+        next: None,
+    };
+    for _ in 0..999999 {
+        list = List {
+            value: Counted(drops.clone()),
+            next: Some(Box::new(list)),
+        };
+    }
+    assert_eq!(Rc::strong_count(&list.value.0), 1000001);
+    drop(list);
+    assert_eq!(drops.get(), 1000000);
+}
+
+#[test]
+fn test_drop_iteratively() {
+    let drops = Rc::new(Cell::new(0));
+    let mut tree = Tree::Leaf(Counted(drops.clone()));
+    for _ in 0..100000 {
+        let leaf = Tree::Leaf(Counted(drops.clone()));
+        tree = Tree::Branch(vec![tree, leaf]);
+    }
+    if let Tree::Branch(children) = &tree {
+        assert!(matches!(&children[1], Tree::Leaf(Counted(c)) if c.get() == 0));
+    }
+    drop_iteratively(tree);
+    assert_eq!(drops.get(), 100001);
+}
+
+#[test]
+fn test_partial_drop() {
+    let mut list = Node { next: None };
+    for _ in 0..10 {
+        list = Node {
+            next: Some(Box::new(list)),
+        };
+    }
+    let mut rest = list.next.take().unwrap();
+    assert!(rest.next.is_some());
+    tramp::drop_children(&mut *rest);
+    assert!(rest.next.is_none());
+    mem::drop(list);
+}
@@ -0,0 +1,80 @@
+use std::cell::Cell;
+use tramp::{fix, fix2, tramp, BorrowRec};
+
+#[test]
+fn test_deep_recursion() {
+    let count = fix(|this, (n, acc): (u64, u64)| {
+        if n > 0 {
+            this((n - 1, acc + 1))
+        } else {
+            BorrowRec::Ret(acc)
+        }
+    });
+    assert_eq!(tramp(count((1000000, 0))), 1000000);
+    assert_eq!(tramp(count((10, 0))), 10);
+}
+
+#[test]
+fn test_captures_environment() {
+    let items = [3u64, 1, 4, 1, 5, 9, 2, 6];
+    let sum = fix(|this, (i, acc): (usize, u64)| match items.get(i) {
+        Some(&x) => this((i + 1, acc + x)),
+        None => BorrowRec::Ret(acc),
+    });
+    assert_eq!(tramp(sum((0, 0))), 31);
+    assert_eq!(tramp(sum((4, 0))), 22);
+}
+
+#[test]
+fn test_not_tail_recursive() {
+    let sum = fix(|this, n: u64| {
+        if n > 0 {
+            this(n - 1).map(move |x| x + n)
+        } else {
+            BorrowRec::Ret(0)
+        }
+    });
+    assert_eq!(tramp(sum(100000)), 5000050000);
+}
+
+#[test]
+fn test_calls_are_lazy() {
+    let steps = Cell::new(0);
+    let count = fix(|this, n: u64| {
+        steps.set(steps.get() + 1);
+        if n > 0 {
+            this(n - 1)
+        } else {
+            BorrowRec::Ret(0)
+        }
+    });
+    let res = count(10);
+    assert_eq!(steps.get(), 0);
+    assert_eq!(tramp(res), 0);
+    assert_eq!(steps.get(), 11);
+}
+
+#[test]
+fn test_mutual_recursion() {
+    let (is_even, is_odd) = fix2(
+        |_, is_odd, n: u64| {
+            if n > 0 {
+                is_odd(n - 1)
+            } else {
+                BorrowRec::Ret(true)
+            }
+        },
+        |is_even, _, n: u64| {
+            if n > 0 {
+                is_even(n - 1)
+            } else {
+                BorrowRec::Ret(false)
+            }
+        },
+    );
+    assert!(tramp(is_even(1000000)));
+    assert!(!tramp(is_odd(1000000)));
+    assert!(tramp(is_odd(7)));
+    let is_even2 = is_even.clone();
+    assert!(!tramp(is_even2(7)));
+}
@@ -0,0 +1,58 @@
+#[macro_use]
+extern crate tramp;
+
+use std::ops::ControlFlow;
+use tramp::{tramp, tramp_with_fuel, BorrowRec, Rec};
+
+fn is_even_rec(x: u128) -> Rec<bool> {
+    if x > 0 {
+        rec_call!(is_odd_rec(x - 1))
+    } else {
+        rec_ret!(true)
+    }
+}
+
+fn is_odd_rec(x: u128) -> Rec<bool> {
+    if x > 0 {
+        rec_call!(is_even_rec(x - 1))
+    } else {
+        rec_ret!(false)
+    }
+}
+
+#[test]
+fn test_resume_with_fuel() {
+    let mut res = is_even_rec(10001);
+    let mut suspensions = 0;
+    let even = loop {
+        match tramp_with_fuel(res, 1000) {
+            Ok(even) => break even,
+            Err(suspended) => {
+                suspensions += 1;
+                res = suspended;
+            },
+        }
+    };
+    assert!(!even);
+    assert_eq!(suspensions, 10);
+
+    let suspended = tramp_with_fuel(is_odd_rec(5000), 10).unwrap_err();
+    assert!(!tramp(suspended));
+}
+
+#[test]
+fn test_step() {
+    let mut res = BorrowRec::defer(|| is_even_rec(3)).map(|even| !even);
+    let mut steps = 0;
+    let odd = loop {
+        match res.step() {
+            ControlFlow::Break(odd) => break odd,
+            ControlFlow::Continue(next) => {
+                steps += 1;
+                res = next;
+            },
+        }
+    };
+    assert!(odd);
+    assert!(steps >= 5);
+}
@@ -0,0 +1,78 @@
+#[macro_use]
+extern crate tramp;
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Wake, Waker},
+};
+use tramp::{tramp_future, Rec};
+
+struct CountingWaker {
+    wakes: AtomicUsize,
+}
+
+impl Wake for CountingWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.wakes.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Polls the future until it is ready, checking that it asked to be woken
+/// up every time it returned `Pending`. Returns the output and the number
+/// of polls.
+fn block_on<F>(mut future: F) -> (F::Output, usize)
+where
+    F: Future + Unpin,
+{
+    let waker = Arc::new(CountingWaker {
+        wakes: AtomicUsize::new(0),
+    });
+    let task_waker = Waker::from(waker.clone());
+    let mut cx = Context::from_waker(&task_waker);
+    let mut polls = 0;
+    loop {
+        polls += 1;
+        match Pin::new(&mut future).poll(&mut cx) {
+            Poll::Ready(x) => break (x, polls),
+            Poll::Pending => {
+                assert_eq!(waker.wakes.load(Ordering::Relaxed), polls)
+            },
+        }
+    }
+}
+
+fn is_even_rec(x: u128) -> Rec<bool> {
+    if x > 0 {
+        rec_call!(is_odd_rec(x - 1))
+    } else {
+        rec_ret!(true)
+    }
+}
+
+fn is_odd_rec(x: u128) -> Rec<bool> {
+    if x > 0 {
+        rec_call!(is_even_rec(x - 1))
+    } else {
+        rec_ret!(false)
+    }
+}
+
+#[test]
+fn test_yields_to_executor() {
+    let (even, polls) = block_on(tramp_future(is_even_rec(100_000), 1000));
+    assert!(even);
+    assert_eq!(polls, 100);
+
+    let (odd, polls) = block_on(tramp_future(is_odd_rec(10), 1000));
+    assert!(!odd);
+    assert_eq!(polls, 1);
+}
@@ -0,0 +1,131 @@
+#[macro_use]
+extern crate tramp;
+
+use tramp::{CallGraph, Rec};
+
+fn is_even(x: u128) -> Rec<bool> {
+    if x > 0 {
+        rec_call!(is_odd(x - 1))
+    } else {
+        rec_ret!(true)
+    }
+}
+
+fn is_odd(x: u128) -> Rec<bool> {
+    if x > 0 {
+        rec_call!(is_even(x - 1))
+    } else {
+        rec_ret!(false)
+    }
+}
+
+fn count(n: u64, acc: u64) -> Rec<u64> {
+    if n > 0 {
+        rec_call!(count(n - 1, acc + 1))
+    } else {
+        rec_ret!(acc)
+    }
+}
+
+fn start_count(n: u64) -> Rec<u64> {
+    rec_call!(count(n, 0))
+}
+
+/// Counts to `n` twice, the second time from a continuation.
+fn count_twice(n: u64) -> Rec<u64> {
+    start_count(n).and_then(move |x| start_count(n).map(move |y| x + y))
+}
+
+fn start_count_twice(n: u64) -> Rec<u64> {
+    rec_call!(count_twice(n))
+}
+
+#[test]
+fn test_mutual_recursion() {
+    let mut graph = CallGraph::new();
+    assert!(graph.run_from("is_even", is_even(10)));
+    if cfg!(feature = "debug-info") {
+        assert_eq!(graph.calls("is_odd"), 5);
+        assert_eq!(graph.calls("is_even"), 5);
+        assert_eq!(graph.edge("is_odd", "is_even"), 5);
+        assert_eq!(graph.edge("is_even", "is_odd"), 5);
+        assert_eq!(graph.edge("is_odd", "is_odd"), 0);
+        let edges = graph.edges().collect::<Vec<_>>();
+        assert_eq!(
+            edges,
+            vec![("is_even", "is_odd", 5), ("is_odd", "is_even", 5)]
+        );
+    } else {
+        assert_eq!(graph.calls("<unknown>"), 10);
+        assert_eq!(graph.edge("is_even", "<unknown>"), 1);
+        assert_eq!(graph.edge("<unknown>", "<unknown>"), 9);
+    }
+}
+
+#[test]
+fn test_continuations() {
+    let mut graph = CallGraph::new();
+    assert_eq!(graph.run_from("count_twice", count_twice(3)), 6);
+    let name = if cfg!(feature = "debug-info") {
+        "count"
+    } else {
+        "<unknown>"
+    };
+    assert_eq!(graph.calls(name), 8);
+    assert_eq!(graph.edge("count_twice", name), 2);
+    assert_eq!(graph.edge(name, name), 6);
+
+    // Continuations chained by a callee are attributed to it.
+    let mut graph = CallGraph::new();
+    assert_eq!(graph.run(start_count_twice(3)), 6);
+    if cfg!(feature = "debug-info") {
+        assert_eq!(graph.calls("count_twice"), 1);
+        assert_eq!(graph.edge("count_twice", "count"), 2);
+        assert_eq!(graph.edge("count", "count"), 6);
+    }
+}
+
+#[test]
+fn test_accumulates_runs() {
+    let mut graph = CallGraph::new();
+    assert_eq!(graph.run(count(3, 0)), 3);
+    assert_eq!(graph.run(count(4, 0)), 4);
+    let name = if cfg!(feature = "debug-info") {
+        "count"
+    } else {
+        "<unknown>"
+    };
+    assert_eq!(graph.calls(name), 7);
+    // The first call of each run has no caller.
+    assert_eq!(graph.edge(name, name), 5);
+}
+
+#[test]
+fn test_no_calls() {
+    let mut graph = CallGraph::new();
+    assert_eq!(graph.run(count(0, 0)), 0);
+    assert_eq!(graph.edges().count(), 0);
+    assert_eq!(graph.to_string(), "digraph calls {\n}");
+}
+
+#[test]
+fn test_dot() {
+    let mut graph = CallGraph::new();
+    assert!(graph.run(is_even(4)));
+    let dot = graph.to_string();
+    if cfg!(feature = "debug-info") {
+        assert_eq!(
+            dot,
+            "digraph calls {\n    \"is_even\" [label=\"is_even (2)\"];\n    \
+             \"is_odd\" [label=\"is_odd (2)\"];\n    \
+             \"is_even\" -> \"is_odd\" [label=\"1\"];\n    \
+             \"is_odd\" -> \"is_even\" [label=\"2\"];\n}"
+        );
+    } else {
+        assert_eq!(
+            dot,
+            "digraph calls {\n    \"<unknown>\" [label=\"<unknown> (4)\"];\n    \
+             \"<unknown>\" -> \"<unknown>\" [label=\"3\"];\n}"
+        );
+    }
+}
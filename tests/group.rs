@@ -0,0 +1,60 @@
+use tramp_macros::tramp_group;
+
+// Not the greatest way of computing "is even" or "is odd".
+tramp_group! {
+    fn is_even(x: u128) -> bool {
+        if x > 0 {
+            rec_call!(is_odd(x - 1))
+        } else {
+            rec_ret!(true)
+        }
+    }
+
+    fn is_odd(x: u128) -> bool {
+        if x > 0 {
+            rec_call!(is_even(x - 1))
+        } else {
+            rec_ret!(false)
+        }
+    }
+}
+
+// Parses a list of signed numbers, one state per kind of token.
+tramp_group! {
+    fn sign(input: &[u8], total: i64) -> Option<i64> {
+        match input.split_first() {
+            Some((b'-', rest)) => rec_call!(number(rest, total, -1, 0)),
+            Some(_) => rec_call!(number(input, total, 1, 0)),
+            None => rec_ret!(Some(total)),
+        }
+    }
+
+    fn number(input: &[u8], total: i64, sign: i64, acc: i64) -> Option<i64> {
+        match input.split_first() {
+            Some((b',', rest)) => rec_call!(sign(rest, total + sign * acc)),
+            Some((digit @ b'0'..=b'9', rest)) => {
+                let acc = acc * 10 + i64::from(digit - b'0');
+                rec_call!(number(rest, total, sign, acc))
+            },
+            Some(_) => rec_ret!(None),
+            None => rec_ret!(Some(total + sign * acc)),
+        }
+    }
+}
+
+#[test]
+fn test_oddness() {
+    for i in 10000..10050 {
+        assert_eq!(is_even(i), i & 1 == 0);
+        assert_eq!(is_odd(i), i & 1 == 1);
+    }
+}
+
+#[test]
+fn test_borrowed_args() {
+    assert_eq!(sign(b"12,-5,3", 0), Some(10));
+    assert_eq!(sign(b"12,x", 0), None);
+
+    let input = "1,".repeat(100000);
+    assert_eq!(sign(input.as_bytes(), 0), Some(100000));
+}
@@ -0,0 +1,138 @@
+#![cfg(feature = "std")]
+
+#[macro_use]
+extern crate tramp;
+
+use std::{
+    cell::RefCell,
+    panic::{self, AssertUnwindSafe},
+    thread,
+};
+use tramp::{tramp, tramp_hybrid, BorrowRec, Hybrid, Rec};
+
+fn count(n: u64, acc: u64) -> Rec<u64> {
+    if n > 0 {
+        rec_call!(direct => count(n - 1, acc + 1))
+    } else {
+        rec_ret!(acc)
+    }
+}
+
+fn count_delayed(n: u64, acc: u64) -> Rec<u64> {
+    if n > 0 {
+        rec_call!(count_delayed(n - 1, acc + 1))
+    } else {
+        rec_ret!(acc)
+    }
+}
+
+// Keeps `total` borrowed across the call.
+fn add_up(total: &RefCell<u64>, n: u64) -> BorrowRec<'_, u64> {
+    let mut sum = total.borrow_mut();
+    if n == 0 {
+        return BorrowRec::Ret(*sum);
+    }
+    *sum += 1;
+    rec_call!(add_up(total, n - 1))
+}
+
+fn add_up_direct(total: &RefCell<u64>, n: u64) -> BorrowRec<'_, u64> {
+    let mut sum = total.borrow_mut();
+    if n == 0 {
+        return BorrowRec::Ret(*sum);
+    }
+    *sum += 1;
+    rec_call!(direct => add_up_direct(total, n - 1))
+}
+
+fn is_ret<T>(res: &BorrowRec<T>) -> bool {
+    matches!(res, BorrowRec::Ret(_))
+}
+
+#[test]
+fn test_deep_recursion() {
+    assert_eq!(tramp_hybrid(count(1000000, 0)), 1000000);
+}
+
+#[test]
+fn test_shallow_calls_are_direct() {
+    let res = Hybrid::new().enter(|| count(100, 0));
+    assert!(is_ret(&res));
+    assert_eq!(tramp(res), 100);
+}
+
+#[test]
+fn test_plain_calls_are_delayed() {
+    let res = Hybrid::new().enter(|| count_delayed(100, 0));
+    assert!(!is_ret(&res));
+    assert_eq!(Hybrid::new().run(res), 100);
+}
+
+#[test]
+fn test_locals_alive_in_direct_calls() {
+    let total = RefCell::new(1);
+    assert_eq!(Hybrid::new().run(add_up(&total, 10)), 11);
+
+    // The direct call is made while the caller still borrows `total`.
+    let total = RefCell::new(1);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        Hybrid::new().run(add_up_direct(&total, 10))
+    }));
+    assert!(result.is_err());
+    // The trampoline makes the calls once the borrows are over.
+    let total = RefCell::new(1);
+    assert_eq!(tramp(add_up_direct(&total, 10)), 11);
+}
+
+#[test]
+fn test_calls_are_delayed_outside() {
+    let res = count(100, 0);
+    assert!(!is_ret(&res));
+    Hybrid::new().enter(|| ());
+    let res = count(100, 0);
+    assert!(!is_ret(&res));
+}
+
+#[test]
+fn test_max_depth() {
+    let hybrid = Hybrid::new().max_depth(10);
+    assert!(is_ret(&hybrid.enter(|| count(10, 0))));
+    let res = hybrid.enter(|| count(11, 0));
+    assert!(!is_ret(&res));
+    assert_eq!(hybrid.run(res), 11);
+
+    let res = Hybrid::new().max_depth(0).enter(|| count(1, 0));
+    assert!(!is_ret(&res));
+}
+
+#[test]
+fn test_red_zone() {
+    let res = Hybrid::new().red_zone(usize::MAX).enter(|| count(10, 0));
+    if cfg!(target_os = "linux") {
+        assert!(!is_ret(&res));
+    } else {
+        assert!(is_ret(&res));
+    }
+}
+
+#[test]
+fn test_small_stack() {
+    let worker = thread::Builder::new()
+        .stack_size(512 * 1024)
+        .spawn(|| {
+            let hybrid = Hybrid::new().red_zone(128 * 1024);
+            hybrid.run(count(1000000, 0))
+        })
+        .unwrap();
+    assert_eq!(worker.join().unwrap(), 1000000);
+}
+
+#[test]
+fn test_mode_restored_after_panic() {
+    let result = panic::catch_unwind(|| {
+        Hybrid::new().enter(|| -> () { panic!("failed") })
+    });
+    assert!(result.is_err());
+    assert!(!is_ret(&count(1, 0)));
+    assert!(is_ret(&Hybrid::new().enter(|| count(1, 0))));
+}
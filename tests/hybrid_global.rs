@@ -0,0 +1,57 @@
+// Runs with or without the `std` feature, in its own test binary since a
+// global hybrid mode may make direct calls on any thread.
+
+#[macro_use]
+extern crate tramp;
+
+use std::thread;
+use tramp::{tramp, BorrowRec, Hybrid, Rec};
+
+fn count(n: u64, acc: u64) -> Rec<u64> {
+    if n > 0 {
+        rec_call!(direct => count(n - 1, acc + 1))
+    } else {
+        rec_ret!(acc)
+    }
+}
+
+fn count_delayed(n: u64, acc: u64) -> Rec<u64> {
+    if n > 0 {
+        rec_call!(count_delayed(n - 1, acc + 1))
+    } else {
+        rec_ret!(acc)
+    }
+}
+
+fn is_ret<T>(res: &BorrowRec<T>) -> bool {
+    matches!(res, BorrowRec::Ret(_))
+}
+
+#[test]
+fn test_global_mode() {
+    // Other threads only make direct calls in a global mode without `std`.
+    let direct = Hybrid::new().enter(|| {
+        let worker = thread::spawn(|| is_ret(&count(10, 0)));
+        worker.join().unwrap()
+    });
+    assert!(!direct);
+    let res = Hybrid::new().enter(|| count(10, 0));
+    assert_eq!(is_ret(&res), cfg!(feature = "std"));
+    assert_eq!(tramp(res), 10);
+
+    let hybrid = Hybrid::new().max_depth(10).global();
+    for _ in 0..3 {
+        assert!(is_ret(&hybrid.enter(|| count(10, 0))));
+        let res = hybrid.enter(|| count(11, 0));
+        assert!(!is_ret(&res));
+        assert_eq!(hybrid.run(res), 11);
+    }
+    assert_eq!(hybrid.run(count(1000000, 0)), 1000000);
+    assert!(!is_ret(&hybrid.enter(|| count_delayed(10, 0))));
+
+    // A mode which is not global turns direct calls off inside a global one
+    // without `std`.
+    let res = hybrid.enter(|| Hybrid::new().enter(|| count(10, 0)));
+    assert_eq!(is_ret(&res), cfg!(feature = "std"));
+    assert!(!is_ret(&count(1, 0)));
+}
@@ -0,0 +1,71 @@
+use tramp::{tramp_join, JoinRec};
+
+/// The number of nodes of a complete binary tree of the given depth.
+fn nodes(depth: u32) -> JoinRec<'static, u64> {
+    if depth == 0 {
+        return JoinRec::Ret(1);
+    }
+    let subs = vec![
+        JoinRec::defer(move || nodes(depth - 1)),
+        JoinRec::defer(move || nodes(depth - 1)),
+    ];
+    JoinRec::join(subs, |counts| JoinRec::Ret(1 + counts[0] + counts[1]))
+}
+
+/// The length of a list whose nodes each fork the rest of the list.
+fn length(n: u64) -> JoinRec<'static, u64> {
+    if n == 0 {
+        return JoinRec::Ret(0);
+    }
+    let subs = vec![JoinRec::defer(move || length(n - 1)), JoinRec::Ret(1)];
+    JoinRec::join(subs, |counts| JoinRec::Ret(counts.iter().sum()))
+}
+
+#[test]
+fn test_tree() {
+    assert_eq!(tramp_join(nodes(0)), 1);
+    assert_eq!(tramp_join(nodes(10)), 2047);
+}
+
+#[test]
+fn test_deep_recursion() {
+    assert_eq!(tramp_join(length(1000000)), 1000000);
+}
+
+#[test]
+fn test_results_in_order() {
+    let subs = (0..10).map(JoinRec::Ret).collect();
+    let res = JoinRec::join(subs, |xs| {
+        JoinRec::Ret(xs.iter().fold(0, |acc, x| acc * 10 + x))
+    });
+    assert_eq!(tramp_join(res), 123456789);
+}
+
+#[test]
+fn test_empty_join() {
+    let res = JoinRec::join(Vec::new(), |xs: Vec<usize>| {
+        JoinRec::Ret(xs.len())
+    });
+    assert_eq!(tramp_join(res), 0);
+}
+
+#[test]
+fn test_borrowing() {
+    fn max(items: &[u32]) -> JoinRec<'_, Option<u32>> {
+        match items {
+            [] => JoinRec::Ret(None),
+            [x] => JoinRec::Ret(Some(*x)),
+            _ => {
+                let (left, right) = items.split_at(items.len() / 2);
+                let subs = vec![
+                    JoinRec::defer(move || max(left)),
+                    JoinRec::defer(move || max(right)),
+                ];
+                JoinRec::join(subs, |xs| JoinRec::Ret(xs[0].max(xs[1])))
+            },
+        }
+    }
+
+    let items = vec![3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5];
+    assert_eq!(tramp_join(max(&items)), Some(9));
+}
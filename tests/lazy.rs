@@ -0,0 +1,107 @@
+extern crate tramp;
+
+use std::{
+    cell::Cell,
+    panic::{self, AssertUnwindSafe},
+};
+use tramp::{tramp, tramp_with_fuel, BorrowRec, Lazy};
+
+#[test]
+fn test_later_cached() {
+    let runs = Cell::new(0);
+    let value = Lazy::later(|| {
+        runs.set(runs.get() + 1);
+        BorrowRec::Ret(42)
+    });
+    let shared = value.clone();
+    assert!(!value.is_known());
+    assert_eq!(value.force(), 42);
+    assert!(shared.is_known());
+    assert_eq!(shared.force(), 42);
+    assert_eq!(runs.get(), 1);
+}
+
+#[test]
+fn test_always_recomputed() {
+    let runs = Cell::new(0);
+    let value = Lazy::always(|| {
+        runs.set(runs.get() + 1);
+        BorrowRec::Ret(runs.get())
+    });
+    assert_eq!(value.force(), 1);
+    assert_eq!(value.force(), 2);
+    assert!(!value.is_known());
+}
+
+#[test]
+fn test_long_chains() {
+    let mut value = Lazy::now(0u64);
+    let mut values = Vec::new();
+    for _ in 0..100000 {
+        let prev = value.clone();
+        value = Lazy::later(move || prev.eval().map(|x| x + 1));
+        values.push(value.clone());
+    }
+    assert_eq!(value.force(), 100000);
+    assert!(values.iter().all(Lazy::is_known));
+
+    // Both halves of the sum depend on the same cached values.
+    let sum = Lazy::later(move || {
+        let a = values[500].eval();
+        let b = values[99999].eval();
+        a.and_then(move |a| b.map(move |b| a + b))
+    });
+    assert_eq!(tramp(sum.eval()), 501 + 100000);
+}
+
+#[test]
+#[should_panic(expected = "lazy value depends on itself")]
+fn test_cycle() {
+    let slot = std::rc::Rc::new(Cell::new(None::<Lazy<u32>>));
+    let inner = slot.clone();
+    let value = Lazy::later(move || {
+        let this: Lazy<u32> = inner.take().unwrap();
+        this.eval()
+    });
+    slot.set(Some(value.clone()));
+    value.force();
+}
+
+#[test]
+fn test_poisoned_by_dropped_computation() {
+    let value = Lazy::later(|| {
+        let mut res = BorrowRec::Ret(0u64);
+        for _ in 0..100 {
+            res = BorrowRec::defer(move || res).map(|x| x + 1);
+        }
+        res
+    });
+    let suspended = tramp_with_fuel(value.eval(), 10).unwrap_err();
+    drop(suspended);
+    let res = panic::catch_unwind(AssertUnwindSafe(|| value.force()));
+    let payload = res.unwrap_err();
+    assert_eq!(
+        payload.downcast_ref::<&str>(),
+        Some(&"lazy value poisoned by an unfinished computation")
+    );
+}
+
+#[test]
+#[should_panic(expected = "lazy value poisoned by an unfinished computation")]
+fn test_poisoned_by_panic() {
+    let value = Lazy::later(|| -> BorrowRec<u64> { panic!("failed") });
+    let res = panic::catch_unwind(AssertUnwindSafe(|| value.force()));
+    assert!(res.is_err());
+    value.force();
+}
+
+#[test]
+fn test_drop_forced_chain() {
+    let mut value = Lazy::now(0u64);
+    for _ in 0..300000 {
+        let prev = value.clone();
+        value = Lazy::later(move || prev.eval().map(|x| x + 1));
+    }
+    assert_eq!(value.force(), 300000);
+    drop(value);
+}
@@ -0,0 +1,117 @@
+use tramp_macros::tramp;
+
+#[tramp]
+fn count(n: u64, acc: u64) -> u64 {
+    if n > 0 {
+        count(n - 1, acc + 1)
+    } else {
+        acc
+    }
+}
+
+#[tramp]
+fn gcd(a: u64, b: u64) -> u64 {
+    match b {
+        0 => a,
+        _ => gcd(b, a % b),
+    }
+}
+
+#[tramp]
+fn find<T: PartialEq>(items: &[T], item: &T, index: usize) -> Option<usize> {
+    if index == items.len() {
+        return None;
+    }
+    if items[index] == *item {
+        return Some(index);
+    }
+    find(items, item, index + 1)
+}
+
+#[tramp]
+fn sum_fields(
+    fields: &[&str],
+    acc: i64,
+) -> Result<i64, std::num::ParseIntError> {
+    let (field, rest) = match fields.split_first() {
+        Some(split) => split,
+        None => return Ok(acc),
+    };
+    let n: i64 = field.trim().parse()?;
+    sum_fields(rest, acc + n)
+}
+
+#[tramp]
+fn push_down(n: u32, out: &mut Vec<u32>) {
+    if n == 0 {
+        return;
+    }
+    out.push(n);
+    push_down(n - 1, out)
+}
+
+// Written with a trailing `return` on purpose.
+#[allow(clippy::needless_return)]
+#[tramp]
+fn count_with_returns(n: u64, acc: u64) -> u64 {
+    if n > 0 {
+        return count_with_returns(n - 1, acc + 1);
+    }
+    return acc;
+}
+
+#[tramp]
+fn sum_iter(it: &mut impl Iterator<Item = u32>, acc: u64) -> u64 {
+    match it.next() {
+        Some(x) => sum_iter(it, acc + u64::from(x)),
+        None => acc,
+    }
+}
+
+#[tramp]
+fn last(mut it: impl Iterator<Item = u32>, prev: Option<u32>) -> Option<u32> {
+    match it.next() {
+        Some(x) => last(it, Some(x)),
+        None => prev,
+    }
+}
+
+#[test]
+fn test_tail_calls() {
+    assert_eq!(count(1000000, 0), 1000000);
+    assert_eq!(gcd(1071, 462), 21);
+}
+
+#[test]
+fn test_borrowed_generic_args() {
+    let items = (0..100000).collect::<Vec<u32>>();
+    assert_eq!(find(&items, &99999, 0), Some(99999));
+    assert_eq!(find(&items, &100000, 0), None);
+}
+
+#[test]
+fn test_question_mark() {
+    let fields = vec!["1"; 100000];
+    assert_eq!(sum_fields(&fields, 0), Ok(100000));
+    assert!(sum_fields(&["1", "x", "3"], 0).is_err());
+}
+
+#[test]
+fn test_unit_return() {
+    let mut out = Vec::new();
+    push_down(100000, &mut out);
+    assert_eq!(out.len(), 100000);
+    assert_eq!(out[0], 100000);
+}
+
+#[test]
+fn test_trailing_return() {
+    assert_eq!(count_with_returns(100000, 0), 100000);
+}
+
+#[test]
+fn test_impl_trait_args() {
+    assert_eq!(sum_iter(&mut (0..100000), 0), 4999950000);
+    assert_eq!(last(0..100000, None), Some(99999));
+    assert_eq!(last(0..0, None), None);
+}
@@ -0,0 +1,105 @@
+use std::panic;
+use tramp::{tramp_memo, BorrowRec, Memo, MemoRec, Rec};
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.as_bytes();
+    let b = b.as_bytes();
+    tramp_memo((a.len(), b.len()), |(i, j), rec| {
+        if i == 0 {
+            return BorrowRec::Ret(j);
+        }
+        if j == 0 {
+            return BorrowRec::Ret(i);
+        }
+        let cost = usize::from(a[i - 1] != b[j - 1]);
+        rec.call_all([(i - 1, j), (i, j - 1), (i - 1, j - 1)])
+            .map(move |v| (v[0] + 1).min(v[1] + 1).min(v[2] + cost))
+    })
+}
+
+#[test]
+fn test_edit_distance() {
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+    assert_eq!(edit_distance("", "abc"), 3);
+    assert_eq!(edit_distance("flaw", "lawn"), 2);
+    assert_eq!(edit_distance("same", "same"), 0);
+}
+
+#[test]
+fn test_deep_recursion() {
+    let sum = tramp_memo(300000u64, |n, rec| {
+        if n == 0 {
+            BorrowRec::Ret(0u64)
+        } else {
+            rec.call(n - 1).map(move |x| x + n)
+        }
+    });
+    assert_eq!(sum, 45000150000);
+}
+
+fn fib(n: u64, rec: &MemoRec<'static, u64, u64>) -> Rec<u64> {
+    if n < 2 {
+        Rec::Ret(n)
+    } else {
+        rec.call_all([n - 1, n - 2]).map(|v| v[0] + v[1])
+    }
+}
+
+#[test]
+fn test_statistics() {
+    let mut memo = Memo::new();
+    assert_eq!(memo.run(20, fib), 6765);
+    assert_eq!(memo.len(), 21);
+    assert_eq!(memo.misses(), 21);
+    assert_eq!(memo.hits(), 18);
+    assert_eq!(memo.get(&10), Some(&55));
+
+    assert_eq!(memo.run(15, fib), 610);
+    assert_eq!(memo.misses(), 21);
+    assert_eq!(memo.hits(), 19);
+
+    memo.clear();
+    assert!(memo.is_empty());
+    assert_eq!(memo.run(15, fib), 610);
+    assert_eq!(memo.misses(), 37);
+}
+
+#[test]
+fn test_self_dependency() {
+    let result = panic::catch_unwind(|| {
+        tramp_memo(3u64, |n, rec| -> BorrowRec<u64> {
+            if n == 0 {
+                rec.call(3)
+            } else {
+                rec.call(n - 1)
+            }
+        })
+    });
+    let payload = result.unwrap_err();
+    assert_eq!(
+        payload.downcast_ref::<&str>(),
+        Some(&"memoized value depends on itself")
+    );
+}
+
+#[test]
+fn test_panic_keeps_cache() {
+    let mut memo = Memo::new();
+    assert_eq!(memo.run(10, fib), 55);
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        memo.run(30, |n, rec| {
+            fib(n, rec).map(move |x| {
+                if n == 25 {
+                    panic!("failed computation");
+                }
+                x
+            })
+        })
+    }));
+    assert!(result.is_err());
+    assert_eq!(memo.len(), 25);
+    assert_eq!(memo.get(&24), Some(&46368));
+    assert_eq!(memo.misses(), 31);
+    assert_eq!(memo.run(24, fib), 46368);
+    assert_eq!(memo.misses(), 31);
+}
@@ -0,0 +1,79 @@
+#[macro_use]
+extern crate tramp;
+
+use std::ops::ControlFlow;
+use tramp::{tramp, tramp_observed, BorrowRec, Observer, Rec};
+
+fn count(n: u64, acc: u64) -> Rec<u64> {
+    if n > 0 {
+        rec_call!(count(n - 1, acc + 1))
+    } else {
+        rec_ret!(acc)
+    }
+}
+
+/// Records what kind of step the trampoline is at, and the result.
+#[derive(Default)]
+struct Log {
+    calls: usize,
+    binds: usize,
+    result: Option<u64>,
+}
+
+impl<'a> Observer<BorrowRec<'a, u64>> for Log {
+    fn on_step(&mut self, res: &BorrowRec<'a, u64>) -> ControlFlow<()> {
+        match res {
+            BorrowRec::Call(_) => self.calls += 1,
+            BorrowRec::Bind(_) => self.binds += 1,
+            BorrowRec::Ret(_) => (),
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn on_ret(&mut self, result: &u64) {
+        self.result = Some(*result);
+    }
+}
+
+/// Stops after a number of steps.
+struct Quota(usize);
+
+impl<'a, T> Observer<BorrowRec<'a, T>> for Quota {
+    fn on_step(&mut self, _: &BorrowRec<'a, T>) -> ControlFlow<()> {
+        match self.0.checked_sub(1) {
+            Some(left) => {
+                self.0 = left;
+                ControlFlow::Continue(())
+            },
+            None => ControlFlow::Break(()),
+        }
+    }
+}
+
+#[test]
+fn test_observe_steps() {
+    let mut log = Log::default();
+    let res = tramp_observed(count(1000, 0), &mut log);
+    assert_eq!(res.ok(), Some(1000));
+    assert_eq!(log.calls, 1000);
+    assert_eq!(log.binds, 0);
+    assert_eq!(log.result, Some(1000));
+
+    let mut log = Log::default();
+    let res = count(100, 0).map(|x| x * 2);
+    assert_eq!(tramp_observed(res, &mut log).ok(), Some(200));
+    assert!(log.binds > 100);
+    assert_eq!(log.result, Some(200));
+}
+
+#[test]
+fn test_stop_and_resume() {
+    let mut quota = Quota(500);
+    let suspended = tramp_observed(count(1000, 0), &mut quota).unwrap_err();
+    assert_eq!(quota.0, 0);
+    assert_eq!(tramp(suspended), 1000);
+
+    let mut quota = Quota(0);
+    let res = tramp_observed(BorrowRec::<u64>::Ret(7), &mut quota);
+    assert_eq!(res.ok(), Some(7));
+}
@@ -0,0 +1,118 @@
+#![cfg(feature = "std")]
+
+use std::{
+    collections::HashSet,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread::{self, ThreadId},
+    time::Duration,
+};
+use tramp::{JoinRec, Parallel};
+
+/// The threads which ran a computation.
+#[derive(Default)]
+struct Threads {
+    ids: Mutex<HashSet<ThreadId>>,
+    running: AtomicUsize,
+    max_running: AtomicUsize,
+}
+
+impl Threads {
+    fn enter(&self) {
+        self.ids.lock().unwrap().insert(thread::current().id());
+        let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_running.fetch_max(running, Ordering::SeqCst);
+        thread::sleep(Duration::from_micros(100));
+        self.running.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    fn ids(&self) -> HashSet<ThreadId> {
+        self.ids.lock().unwrap().clone()
+    }
+}
+
+fn sum<'a>(items: &'a [u64], threads: &'a Threads) -> JoinRec<'a, u64> {
+    if items.len() < 100 {
+        threads.enter();
+        return JoinRec::Ret(items.iter().sum());
+    }
+    let (left, right) = items.split_at(items.len() / 2);
+    let subs = vec![
+        JoinRec::defer(move || sum(left, threads)),
+        JoinRec::defer(move || sum(right, threads)),
+    ];
+    JoinRec::join(subs, |sums| JoinRec::Ret(sums.iter().sum()))
+}
+
+fn length(n: u64) -> JoinRec<'static, u64> {
+    if n == 0 {
+        return JoinRec::Ret(0);
+    }
+    let subs = vec![JoinRec::defer(move || length(n - 1)), JoinRec::Ret(1)];
+    JoinRec::join(subs, |counts| JoinRec::Ret(counts.iter().sum()))
+}
+
+#[test]
+fn test_parallel_sum() {
+    let items = (0..10000).collect::<Vec<u64>>();
+    let threads = Threads::default();
+    let parallel = Parallel::new().threads(4).cutoff(4);
+    assert_eq!(parallel.run(sum(&items, &threads)), 49995000);
+    assert!(threads.ids().len() > 1);
+    assert!(threads.max_running.into_inner() <= 4);
+}
+
+#[test]
+fn test_single_thread() {
+    let items = (0..10000).collect::<Vec<u64>>();
+    let threads = Threads::default();
+    let parallel = Parallel::new().threads(1);
+    assert_eq!(parallel.run(sum(&items, &threads)), 49995000);
+    let ids = threads.ids();
+    assert_eq!(ids.len(), 1);
+    assert!(ids.contains(&thread::current().id()));
+}
+
+#[test]
+fn test_zero_cutoff() {
+    let items = (0..10000).collect::<Vec<u64>>();
+    let threads = Threads::default();
+    let parallel = Parallel::new().threads(4).cutoff(0);
+    assert_eq!(parallel.run(sum(&items, &threads)), 49995000);
+    assert_eq!(threads.ids().len(), 1);
+}
+
+#[test]
+fn test_deep_recursion() {
+    let parallel = Parallel::new().threads(4).cutoff(16);
+    assert_eq!(parallel.run(length(1000000)), 1000000);
+}
+
+#[test]
+fn test_deep_recursion_without_cutoff() {
+    let parallel = Parallel::new().threads(1).cutoff(usize::MAX);
+    assert_eq!(parallel.run(length(100000)), 100000);
+    let parallel = Parallel::new().threads(4).cutoff(usize::MAX);
+    assert_eq!(parallel.run(length(100000)), 100000);
+}
+
+#[test]
+fn test_panic() {
+    let res = JoinRec::join(
+        vec![
+            JoinRec::Ret(1),
+            JoinRec::defer(|| panic!("failed sub-computation")),
+        ],
+        |xs: Vec<u64>| JoinRec::Ret(xs.iter().sum()),
+    );
+    let parallel = Parallel::new().threads(2);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| parallel.run(res)));
+    let payload = result.unwrap_err();
+    assert_eq!(
+        payload.downcast_ref::<&str>(),
+        Some(&"failed sub-computation")
+    );
+}
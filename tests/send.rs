@@ -0,0 +1,70 @@
+#[macro_use]
+extern crate tramp;
+
+use std::{sync::mpsc, thread};
+use tramp::{tramp, tramp_with_fuel, BorrowRec, SendRec, SendThunk};
+
+fn is_even_rec(x: u128) -> SendRec<bool> {
+    if x > 0 {
+        send_rec_call!(is_odd_rec(x - 1))
+    } else {
+        send_rec_ret!(true)
+    }
+}
+
+fn is_odd_rec(x: u128) -> SendRec<bool> {
+    if x > 0 {
+        send_rec_call!(is_even_rec(x - 1))
+    } else {
+        send_rec_ret!(false)
+    }
+}
+
+fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+#[test]
+fn test_migrate_between_threads() {
+    let (to_worker, from_main) = mpsc::channel::<SendRec<bool>>();
+    let (to_main, from_worker) = mpsc::channel();
+
+    let worker = thread::spawn(move || {
+        for res in from_main {
+            let res = tramp_with_fuel(res, 1000);
+            if to_main.send(res).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut res = is_even_rec(100001);
+    assert_send_sync(&res);
+    let mut slices = 0;
+    let even = loop {
+        slices += 1;
+        to_worker.send(res).unwrap();
+        match from_worker.recv().unwrap() {
+            Ok(even) => break even,
+            Err(suspended) => {
+                // Run a slice here too, so the computation keeps moving.
+                match tramp_with_fuel(suspended, 1000) {
+                    Ok(even) => break even,
+                    Err(suspended) => res = suspended,
+                }
+            },
+        }
+    };
+    drop(to_worker);
+    worker.join().unwrap();
+
+    assert!(!even);
+    assert_eq!(slices, 51);
+}
+
+#[test]
+fn test_into_borrow_rec() {
+    let res: BorrowRec<bool> = is_odd_rec(1001).into();
+    assert!(!tramp(res.map(|odd| !odd)));
+
+    let thunk = SendThunk::new(|| 6 * 7);
+    assert_eq!(thunk.into_thunk().compute(), 42);
+}
@@ -0,0 +1,47 @@
+extern crate tramp;
+
+use std::{cell::Cell, mem, rc::Rc};
+use tramp::{Thunk, INLINE_SIZE};
+
+struct DropCounter(Rc<Cell<usize>>);
+
+impl Drop for DropCounter {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+#[test]
+fn test_small_and_large_captures() {
+    let small = (3u128, 4u128);
+    let thunk = Thunk::new(move || small.0 * small.1);
+    assert_eq!(thunk.compute(), 12);
+
+    let large = [7u64; 64];
+    assert!(mem::size_of_val(&large) > INLINE_SIZE);
+    let thunk = Thunk::new(move || large.iter().sum::<u64>());
+    assert_eq!(thunk.compute(), 7 * 64);
+}
+
+#[test]
+fn test_captures_dropped_once() {
+    let drops = Rc::new(Cell::new(0));
+
+    let counter = DropCounter(drops.clone());
+    drop(Thunk::new(move || drop(counter)));
+    assert_eq!(drops.get(), 1);
+
+    let counter = DropCounter(drops.clone());
+    Thunk::new(move || drop(counter)).compute();
+    assert_eq!(drops.get(), 2);
+
+    let counter = DropCounter(drops.clone());
+    let padding = [0u8; 256];
+    drop(Thunk::new(move || drop((counter, padding))));
+    assert_eq!(drops.get(), 3);
+
+    let counter = DropCounter(drops.clone());
+    let padding = [0u8; 256];
+    Thunk::new(move || drop((counter, padding))).compute();
+    assert_eq!(drops.get(), 4);
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "std")]
+
+#[macro_use]
+extern crate tramp;
+
+use tramp::Rec;
+
+fn is_even_rec(x: u128) -> Rec<bool> {
+    if x == 5 {
+        panic!("five is not allowed");
+    }
+    if x > 0 {
+        rec_call!(is_odd_rec(x - 1))
+    } else {
+        rec_ret!(true)
+    }
+}
+
+fn is_odd_rec(x: u128) -> Rec<bool> {
+    if x > 0 {
+        rec_call!(is_even_rec(x - 1))
+    } else {
+        rec_ret!(false)
+    }
+}
+
+/// Runs `fun`, which makes a call with `rec_call!`, so that the call and
+/// the line it is made on can be taken in the same statement.
+fn call<T>(fun: impl FnOnce() -> Rec<T>) -> Rec<T> {
+    fun()
+}
+
+#[test]
+fn test_catch_traced() {
+    use tramp::tramp_catch_traced;
+
+    let traced = tramp_catch_traced(is_even_rec(10003), 4).unwrap_err();
+    assert_eq!(traced.message(), Some("five is not allowed"));
+    assert_eq!(traced.backtrace.calls().count(), 4);
+    assert_eq!(traced.backtrace.omitted(), 9994);
+
+    let shown = traced.backtrace.to_string();
+    assert!(shown.starts_with("logical backtrace"));
+    if cfg!(feature = "debug-info") {
+        let labels = traced
+            .backtrace
+            .calls()
+            .map(|call| call.label().unwrap())
+            .collect::<Vec<_>>();
+        // The last call is the one which panicked.
+        let expected = ["is_odd_rec", "is_even_rec"];
+        assert_eq!(labels, [expected, expected].concat());
+    }
+
+    // Calls made through `and_then` are recorded as well.
+    let (res, line) = (call(|| rec_call!(is_odd_rec(3))), line!());
+    let res = res.and_then(|_| is_even_rec(7));
+    let traced = tramp_catch_traced(res, 100).unwrap_err();
+    assert_eq!(traced.backtrace.calls().count(), 6);
+    if cfg!(feature = "debug-info") {
+        let shown = traced.backtrace.to_string();
+        let site = format!("is_odd_rec at tests/trace.rs:{}:", line);
+        assert!(shown.contains(&site), "{}", shown);
+    }
+
+    assert_eq!(tramp_catch_traced(is_odd_rec(3), 0).ok(), Some(true));
+}
+
+#[test]
+#[should_panic(expected = "five is not allowed")]
+fn test_traced_resumes_panic() {
+    tramp::tramp_traced(is_odd_rec(100), 10);
+}
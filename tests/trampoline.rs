@@ -0,0 +1,110 @@
+use std::{
+    future::Future,
+    ops::ControlFlow,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+use tramp::{
+    tramp,
+    tramp_cancellable,
+    tramp_future,
+    tramp_in,
+    tramp_with_fuel,
+    try_tramp,
+    Arena,
+    CancelToken,
+    Hybrid,
+    Trampoline,
+};
+
+// Euclid's algorithm as a hand-written state machine, counting its steps.
+#[derive(Debug)]
+enum Gcd {
+    Step(u64, u64, u32),
+    Done(u64, u32),
+}
+
+impl Trampoline for Gcd {
+    type Output = (u64, u32);
+
+    fn step(self) -> ControlFlow<(u64, u32), Self> {
+        match self {
+            Gcd::Step(a, 0, steps) => {
+                ControlFlow::Continue(Gcd::Done(a, steps))
+            },
+            Gcd::Step(a, b, steps) => {
+                ControlFlow::Continue(Gcd::Step(b, a % b, steps + 1))
+            },
+            Gcd::Done(a, steps) => ControlFlow::Break((a, steps)),
+        }
+    }
+
+    fn try_finish(self) -> Result<(u64, u32), Self> {
+        match self {
+            Gcd::Done(a, steps) => Ok((a, steps)),
+            this => Err(this),
+        }
+    }
+}
+
+#[test]
+fn test_state_machine() {
+    assert_eq!(tramp(Gcd::Step(1071, 462, 0)), (21, 3));
+
+    // Consecutive Fibonacci numbers are the worst case.
+    let start = Gcd::Step(7540113804746346429, 4660046610375530309, 0);
+    let (gcd, steps) = tramp(start);
+    assert_eq!(gcd, 1);
+    assert_eq!(steps, 90);
+}
+
+#[test]
+fn test_drivers() {
+    let suspended = tramp_with_fuel(Gcd::Step(1071, 462, 0), 2).unwrap_err();
+    assert!(matches!(suspended, Gcd::Step(147, 21, 2)));
+    assert_eq!(tramp_with_fuel(suspended, 2).unwrap(), (21, 3));
+
+    let token = CancelToken::new();
+    token.cancel();
+    let res = tramp_cancellable(Gcd::Step(1071, 462, 0), &token, 1);
+    assert!(matches!(res, Err(Gcd::Step(1071, 462, 0))));
+    token.reset();
+    let res = tramp_cancellable(Gcd::Step(1071, 462, 0), &token, 1);
+    assert_eq!(res.unwrap(), (21, 3));
+}
+
+#[test]
+fn test_generic_drivers() {
+    let mut future = tramp_future(Gcd::Step(1071, 462, 0), 2);
+    let mut cx = Context::from_waker(Waker::noop());
+    assert!(Pin::new(&mut future).poll(&mut cx).is_pending());
+    assert_eq!(Pin::new(&mut future).poll(&mut cx), Poll::Ready((21, 3)));
+
+    let arena = Arena::new();
+    assert_eq!(tramp_in(&arena, |_| Gcd::Step(1071, 462, 0)), (21, 3));
+
+    assert_eq!(Hybrid::new().run(Gcd::Step(1071, 462, 0)), (21, 3));
+
+    let checked = CheckedGcd(Gcd::Step(1071, 0, 0));
+    assert_eq!(try_tramp(checked), Err("gcd of zero"));
+    assert_eq!(try_tramp(CheckedGcd(Gcd::Step(1071, 462, 0))), Ok(21));
+}
+
+// Fails when the second number is zero from the start.
+struct CheckedGcd(Gcd);
+
+impl Trampoline for CheckedGcd {
+    type Output = Result<u64, &'static str>;
+
+    fn step(self) -> ControlFlow<Self::Output, Self> {
+        match self.0 {
+            Gcd::Step(_, 0, 0) => ControlFlow::Break(Err("gcd of zero")),
+            gcd => match gcd.step() {
+                ControlFlow::Break((a, _)) => ControlFlow::Break(Ok(a)),
+                ControlFlow::Continue(next) => {
+                    ControlFlow::Continue(CheckedGcd(next))
+                },
+            },
+        }
+    }
+}
@@ -0,0 +1,55 @@
+#[macro_use]
+extern crate tramp;
+
+use std::num::ParseIntError;
+use tramp::{try_tramp, BorrowRec, Rec, TryRec};
+
+#[derive(Debug, PartialEq)]
+enum Error {
+    Parse(ParseIntError),
+    Negative(i64),
+}
+
+impl From<ParseIntError> for Error {
+    fn from(error: ParseIntError) -> Self {
+        Error::Parse(error)
+    }
+}
+
+fn count_down(n: i64, acc: u64) -> Rec<u64> {
+    if n > 0 {
+        rec_call!(count_down(n - 1, acc + n as u64))
+    } else {
+        rec_ret!(acc)
+    }
+}
+
+fn sum_fields(fields: Vec<&'static str>, acc: u64) -> TryRec<u64, Error> {
+    let mut fields = fields;
+    match fields.pop() {
+        Some(field) => {
+            let n: i64 = rec_try!(field.trim().parse());
+            if n < 0 {
+                rec_ret!(Err(Error::Negative(n)))
+            }
+            count_down(n, 0).into_ok().and_then_ok(move |t| {
+                BorrowRec::defer(move || sum_fields(fields, acc + t))
+            })
+        },
+        None => rec_ret!(Ok(acc)),
+    }
+}
+
+#[test]
+fn test_try_tramp() {
+    let fields = vec!["1", " 2", "3 ", "10000"];
+    assert_eq!(try_tramp(sum_fields(fields, 0)), Ok(1 + 3 + 6 + 50005000));
+
+    let fields = vec!["1", "x", "3"];
+    let res = try_tramp(sum_fields(fields, 0));
+    assert!(matches!(res, Err(Error::Parse(_))));
+
+    let mut fields = vec!["1"; 100000];
+    fields[10] = "-4";
+    assert_eq!(try_tramp(sum_fields(fields, 0)), Err(Error::Negative(-4)));
+}
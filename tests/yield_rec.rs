@@ -0,0 +1,48 @@
+#[macro_use]
+extern crate tramp;
+
+use tramp::YieldRec;
+
+enum Tree {
+    Leaf,
+    Node(Box<Tree>, u32, Box<Tree>),
+}
+
+fn walk(tree: &Tree) -> YieldRec<'_, u32> {
+    match tree {
+        Tree::Leaf => YieldRec::Ret,
+        Tree::Node(left, value, right) => YieldRec::defer(move || walk(left))
+            .then(move || rec_yield!(*value => walk(right))),
+    }
+}
+
+fn naturals(n: u64) -> YieldRec<'static, u64> {
+    rec_yield!(n => naturals(n + 1))
+}
+
+#[test]
+fn test_in_order_traversal() {
+    let tree = Tree::Node(
+        Box::new(Tree::Node(Box::new(Tree::Leaf), 1, Box::new(Tree::Leaf))),
+        2,
+        Box::new(Tree::Node(Box::new(Tree::Leaf), 3, Box::new(Tree::Leaf))),
+    );
+    assert_eq!(walk(&tree).into_iter().collect::<Vec<_>>(), [1, 2, 3]);
+
+    let mut deep = Tree::Leaf;
+    for i in 0..100_000 {
+        deep = Tree::Node(Box::new(deep), i, Box::new(Tree::Leaf));
+    }
+    assert!(walk(&deep).into_iter().eq(0..100_000));
+
+    // Drop the left spine iteratively.
+    while let Tree::Node(left, _, _) = deep {
+        deep = *left;
+    }
+}
+
+#[test]
+fn test_lazy() {
+    let first: Vec<u64> = naturals(0).into_iter().take(5).collect();
+    assert_eq!(first, [0, 1, 2, 3, 4]);
+}